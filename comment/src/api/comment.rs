@@ -7,6 +7,7 @@ use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
 use util::axum::auth::UserToken;
 use util::axum::Response;
+use util::policy::Subject;
 use util::response;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -52,7 +53,8 @@ pub async fn change_comment(
     Extension(user): Extension<UserToken>,
     Json(req): Json<CommentChangeRequest>,
 ) -> Response<CommentNode> {
-    response!(change(req.comment, req.comment_id, user.user_id).await)
+    let user = Subject::from_token(&user).await;
+    response!(change(req.comment, req.comment_id, user).await)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -64,5 +66,6 @@ pub async fn delete_comment(
     Extension(user): Extension<UserToken>,
     Json(req): Json<CommentID>,
 ) -> Response<CommentNode> {
-    response!(delete(req.comment_id, user.user_id).await)
+    let user = Subject::from_token(&user).await;
+    response!(delete(req.comment_id, user).await)
 }
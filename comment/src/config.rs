@@ -0,0 +1,52 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use util::config::{check_addr, ClientCredential, DatabaseConfig, PeersConfig, Validate};
+use util::log_init::init::LogConfig;
+use util::pb::tls::TlsFiles;
+use util::trace::TraceConfig;
+
+lazy_static! {
+    pub static ref CONFIG: Config = util::config::load("comment");
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub http_addr: String,
+    pub database: DatabaseConfig,
+    // 调用 login
+    pub peers: PeersConfig,
+    pub client: ClientCredential,
+    // 配置后 grpc 使用 mtls
+    pub tls: Option<TlsFiles>,
+    pub trace: TraceConfig,
+    pub log: LogConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            http_addr: "0.0.0.0:8083".to_string(),
+            database: Default::default(),
+            peers: PeersConfig::from_urls("http://127.0.0.1:8089", "", ""),
+            client: ClientCredential::dev("comment"),
+            tls: None,
+            trace: Default::default(),
+            log: Default::default(),
+        }
+    }
+}
+
+impl Validate for Config {
+    fn validate(&self) -> anyhow::Result<()> {
+        check_addr("http_addr", &self.http_addr)?;
+        self.database.validate()?;
+        self.peers.check(&["validate"], self.tls.is_some())?;
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+        self.trace.validate()?;
+        self.log.validate()?;
+        Ok(())
+    }
+}
@@ -6,6 +6,11 @@ use serde::{Deserialize, Serialize, Serializer};
 use serde_json::{Map, Number, Value};
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use util::metrics::Counter;
+use util::policy::{authorize, Action, Subject};
+
+static COMMENTS_POSTED: Counter =
+    Counter::new("comments_posted_total", "comments and replies posted");
 
 pub struct CommentNode {
     pub comment_id: u64,
@@ -161,28 +166,37 @@ pub async fn comment_to_item(
 ) -> Result<CommentNode> {
     let mut comment = Comment::new(comment, user_name, user_id, item_id, Some(father_id));
     comment.create().await?;
+    COMMENTS_POSTED.inc(&[]);
     Ok(comment.into())
 }
 
 pub async fn change_comment(
     comment_str: String,
     comment_id: u64,
-    user_id: u64,
+    user: Subject,
+) -> Result<CommentNode> {
+    set_comment(comment_str, comment_id, user, Action::Update).await
+}
+
+// 删除只清空内容，保留回复关系
+pub async fn delete_comment(comment_id: u64, user: Subject) -> Result<CommentNode> {
+    set_comment("".to_string(), comment_id, user, Action::Delete).await
+}
+
+async fn set_comment(
+    comment_str: String,
+    comment_id: u64,
+    user: Subject,
+    action: Action,
 ) -> Result<CommentNode> {
     let mut comment = Comment::select_by_id(comment_id)
         .await
         .ok_or(anyhow!("not found"))?;
-    if comment.user_id != user_id {
-        return Err(anyhow!("not user"));
-    }
+    authorize(&user, action, &comment)?;
     comment.change(comment_str).await?;
     Ok(comment.into())
 }
 
-pub async fn delete_comment(comment_id: u64, user_id: u64) -> Result<CommentNode> {
-    change_comment("".to_string(), comment_id, user_id).await
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,8 +4,10 @@ use axum::{
     Router,
 };
 use tower_http::trace::TraceLayer;
+use util::health::Readiness;
 
 mod api;
+mod config;
 mod domain;
 mod repo;
 
@@ -14,20 +16,23 @@ async fn main() {
     // if std::env::var_os("RUST_LOG").is_none() {
     //     std::env::set_var("RUST_LOG", "tower_http=debug,middleware=debug");
     // }
-    util::log_init::init::init();
+    let config = &*config::CONFIG;
+    util::log_init::init::init(&config.log);
+    util::trace::init("comment", &config.trace);
 
     repo::init().await;
-    util::pb::client::init(
-        "http://127.0.0.1:8089".to_string(),
-        "".to_string(),
-        "".to_string(),
-    )
-    .await;
+    util::pb::tls::init(config.tls.clone());
+    util::pb::client::init(&config.peers);
+    util::pb::client::set_client_credentials(
+        config.client.id.clone(),
+        config.client.secret.clone(),
+    );
 
     util::pb::init_url_auth(&[
-        ("/comment_to", "normal"),
-        ("/change_comment", "normal"),
-        ("/delete_comment", "normal"),
+        ("POST /comment_to", "normal"),
+        ("POST /change_comment", "normal"),
+        ("DELETE /delete_comment", "normal"),
+        // /admin/log_filter 由 login 注册为 root
     ])
     .await;
 
@@ -35,11 +40,18 @@ async fn main() {
         .route("/comment_to", post(api::comment::comment_to))
         .route("/change_comment", post(api::comment::change_comment))
         .route("/delete_comment", delete(api::comment::delete_comment))
+        .merge(util::log_init::init::router())
         .layer(middleware::from_fn(util::axum::auth::auth))
         .route("/comment_of", get(api::comment::comments_of))
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(util::axum::metrics::track))
+        .layer(util::trace::TraceContextLayer)
+        .merge(util::metrics::router())
+        .merge(util::health::router(
+            Readiness::new().check("database", repo::ping).peers(),
+        ));
 
-    axum::Server::bind(&"0.0.0.0:8083".parse().unwrap())
+    axum::Server::bind(&config.http_addr.parse().unwrap())
         .serve(app.into_make_service())
         .await
         .unwrap();
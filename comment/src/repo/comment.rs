@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use table_rbs::CreateTable;
 use util::get;
+use util::policy::{Kind, Resource};
 use util::rbatis::init::{get_tx_set_defer, InitItem};
 
 // TODO:所有相关接口
@@ -75,3 +76,10 @@ impl Comment {
         Ok(())
     }
 }
+
+impl Resource for Comment {
+    const KIND: Kind = Kind::Comment;
+    fn owner_id(&self) -> u64 {
+        self.user_id
+    }
+}
@@ -0,0 +1,28 @@
+#!/bin/sh
+# 生成本地测试用的 ca 和证书，不要用于部署，生成的文件不提交
+# 部署时在目标机器上运行或者由证书管理签发
+# 所有服务共用一份证书，既用于 grpc 服务端也用于客户端
+#   SUPERMARKET__TLS__CERT=deploy/certs/service.pem cargo run --bin login
+# 所有服务都需要设置，peers 的地址改为 https://
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 3650 \
+  -subj "/CN=supermarket-dev-ca" \
+  -keyout ca.key -out ca.pem
+
+cat > service.ext << EOF_EXT
+basicConstraints = CA:FALSE
+keyUsage = digitalSignature, keyEncipherment
+extendedKeyUsage = serverAuth, clientAuth
+subjectAltName = DNS:localhost, IP:127.0.0.1
+EOF_EXT
+
+openssl req -newkey rsa:2048 -nodes \
+  -subj "/CN=localhost" \
+  -keyout service.key -out service.csr
+openssl x509 -req -days 3650 -in service.csr \
+  -CA ca.pem -CAkey ca.key -CAcreateserial \
+  -extfile service.ext -out service.pem
+
+rm -f service.csr service.ext ca.srl ca.key
@@ -0,0 +1,68 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::Response;
+use crate::domain::account;
+use crate::domain::trans_to_token::UserToken;
+
+fn get_resp(result: anyhow::Result<()>, err_msg: &str) -> Response<String> {
+    match result {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::from_err(301, e, |e| format!("{},{}", err_msg, e)),
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ChangePasswordRequest {
+    pub old_pass_word: String,
+    pub new_pass_word: String,
+}
+
+pub async fn change_password(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Json<Response<String>> {
+    let result = account::change_password(
+        &user.user_name,
+        &request.old_pass_word,
+        &request.new_pass_word,
+    )
+    .await;
+    Json(get_resp(result, "err in change password"))
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RequestResetRequest {
+    pub user_name: String,
+}
+
+pub async fn request_reset(Json(request): Json<RequestResetRequest>) -> Json<Response<String>> {
+    let result = account::request_reset(&request.user_name).await;
+    Json(get_resp(result, "err in request reset"))
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ResetPasswordRequest {
+    pub user_name: String,
+    pub code: String,
+    pub new_pass_word: String,
+}
+
+pub async fn reset_password(Json(request): Json<ResetPasswordRequest>) -> Json<Response<String>> {
+    let result =
+        account::reset_password(&request.user_name, &request.code, &request.new_pass_word).await;
+    Json(get_resp(result, "err in reset password"))
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeleteAccountRequest {
+    pub user_name: String,
+}
+
+pub async fn delete_account(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<DeleteAccountRequest>,
+) -> Json<Response<String>> {
+    let result = account::delete_account(&user.user_name, &request.user_name).await;
+    Json(get_resp(result, "err in delete account"))
+}
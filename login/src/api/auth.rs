@@ -1,7 +1,10 @@
+use std::net::IpAddr;
+
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
 use crate::api::Response;
+use crate::domain::login_limit;
 use crate::domain::validate_auth::set_role_auth;
 
 #[derive(Deserialize, Serialize)]
@@ -16,3 +19,18 @@ pub async fn add_auth(Json(request): Json<AddAuthRequest>) -> Json<Response<Stri
     };
     Json(Response::ok(result))
 }
+
+#[derive(Deserialize, Serialize)]
+pub struct UnlockUserRequest {
+    pub user_name: String,
+    // 同时解除该 ip 的锁定
+    #[serde(default)]
+    pub ip: Option<IpAddr>,
+}
+pub async fn unlock_user(Json(request): Json<UnlockUserRequest>) -> Json<Response<String>> {
+    let resp = match login_limit::unlock(&request.user_name, request.ip).await {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::err(301, e.to_string()),
+    };
+    Json(resp)
+}
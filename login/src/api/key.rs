@@ -0,0 +1,8 @@
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::domain::trans_to_token::KEY_RING;
+
+pub async fn jwks() -> Json<JwkSet> {
+    Json(KEY_RING.jwks())
+}
@@ -1,8 +1,13 @@
-use axum::Json;
+use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
 
 use crate::api::Response;
-use crate::domain::trans_to_token::{refresh_token, sign_by_req, trans};
+use crate::domain::otp::OtpChallenge;
+use crate::domain::session::ClientInfo;
+use crate::domain::trans_to_token::{
+    logout as logout_token, logout_all as logout_all_token, refresh_token, sign_by_req, trans,
+    verify_otp as verify_otp_token, LoginResult, UserToken,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct LoginResponse {
@@ -10,8 +15,8 @@ pub struct LoginResponse {
     refresh_token: String,
 }
 
-impl LoginResponse {
-    fn new(token: String, rt: String) -> Self {
+impl From<(String, String)> for LoginResponse {
+    fn from((token, rt): (String, String)) -> Self {
         Self {
             token,
             refresh_token: rt,
@@ -19,28 +24,63 @@ impl LoginResponse {
     }
 }
 
+// 需要 otp 时返回 challenge_token，否则直接返回 token
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginStep {
+    Token(LoginResponse),
+    Challenge(OtpChallenge),
+}
+
+impl From<LoginResult> for LoginStep {
+    fn from(result: LoginResult) -> Self {
+        match result {
+            LoginResult::Token(token_rt) => LoginStep::Token(token_rt.into()),
+            LoginResult::Challenge(challenge) => LoginStep::Challenge(challenge),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct LoginRequest {
     pub user_name: String,
     pub pass_word: String,
 }
 
-fn get_resp<F>(
-    result: anyhow::Result<(String, String)>,
-    err_code: i32,
-    err_msg: F,
-) -> Response<LoginResponse>
+fn get_resp<T, R, F>(result: anyhow::Result<T>, err_code: i32, err_msg: F) -> Response<R>
 where
+    R: Serialize + From<T>,
     F: FnOnce(anyhow::Error) -> String,
 {
     match result {
-        Ok((token, rt)) => Response::ok(LoginResponse::new(token, rt)),
-        Err(e) => Response::err(err_code, err_msg(e)),
+        Ok(data) => Response::ok(data.into()),
+        Err(e) => Response::from_err(err_code, e, err_msg),
     }
 }
 
-pub async fn login(Json(user): Json<LoginRequest>) -> Json<Response<LoginResponse>> {
-    let resp = get_resp(trans(user).await, 301, |e| format!("err in login,{}", e));
+pub async fn login(
+    client: ClientInfo,
+    Json(user): Json<LoginRequest>,
+) -> Json<Response<LoginStep>> {
+    let resp = get_resp(trans(user, &client).await, 301, |e| {
+        format!("err in login,{}", e)
+    });
+    Json(resp)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct VerifyOtpRequest {
+    pub challenge_token: String,
+    // otp 或者恢复码
+    pub code: String,
+}
+
+pub async fn verify_otp(
+    client: ClientInfo,
+    Json(request): Json<VerifyOtpRequest>,
+) -> Json<Response<LoginResponse>> {
+    let result = verify_otp_token(&request.challenge_token, &request.code, &client).await;
+    let resp = get_resp(result, 301, |e| format!("err in verify otp,{}", e));
     Json(resp)
 }
 
@@ -49,10 +89,15 @@ pub struct RefreshRequest {
     pub refresh_token: String,
 }
 
-pub async fn refresh(Json(request): Json<RefreshRequest>) -> Json<Response<LoginResponse>> {
-    let resp = get_resp(refresh_token(request.refresh_token).await, 301, |e| {
-        format!("err in refresh,{}", e)
-    });
+pub async fn refresh(
+    client: ClientInfo,
+    Json(request): Json<RefreshRequest>,
+) -> Json<Response<LoginResponse>> {
+    let resp = get_resp(
+        refresh_token(request.refresh_token, &client).await,
+        301,
+        |e| format!("err in refresh,{}", e),
+    );
     Json(resp)
 }
 
@@ -62,9 +107,28 @@ pub struct SignRequest {
     pub pass_word: String,
 }
 
-pub async fn sign(Json(request): Json<SignRequest>) -> Json<Response<LoginResponse>> {
-    let resp = get_resp(sign_by_req(request).await, 301, |e| {
+pub async fn sign(
+    client: ClientInfo,
+    Json(request): Json<SignRequest>,
+) -> Json<Response<LoginResponse>> {
+    let resp = get_resp(sign_by_req(request, &client).await, 301, |e| {
         format!("err in sign,{}", e)
     });
     Json(resp)
 }
+
+pub async fn logout(Extension(user): Extension<UserToken>) -> Json<Response<String>> {
+    let resp = match logout_token(&user).await {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::err(301, format!("err in logout,{}", e)),
+    };
+    Json(resp)
+}
+
+pub async fn logout_all(Extension(user): Extension<UserToken>) -> Json<Response<String>> {
+    let resp = match logout_all_token(user.user_id).await {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::err(301, format!("err in logout all,{}", e)),
+    };
+    Json(resp)
+}
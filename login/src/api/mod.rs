@@ -1,7 +1,22 @@
+use std::net::SocketAddr;
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::{header, StatusCode};
 use serde::Serialize;
 
+use crate::domain::credential_policy::{FieldError, PolicyErr};
+use crate::domain::login_limit::LimitErr;
+use crate::domain::session::ClientInfo;
+
+pub mod account;
 pub mod auth;
+pub mod key;
 pub mod login;
+pub mod otp;
+pub mod role;
+pub mod session;
 pub mod validate;
 
 #[derive(Serialize)]
@@ -9,6 +24,11 @@ pub struct Response<T: Serialize> {
     pub code: i32,
     pub msg: String,
     pub data: Option<T>,
+    // 参数校验失败时每个字段的错误
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl<T> Response<T>
@@ -16,7 +36,13 @@ where
     T: Serialize,
 {
     pub fn new(code: i32, msg: String, data: Option<T>) -> Self {
-        Self { code, msg, data }
+        Self {
+            code,
+            msg,
+            data,
+            errors: vec![],
+            request_id: util::trace::current().map(|ctx| ctx.request_id),
+        }
     }
     pub fn ok(data: T) -> Self {
         Self::new(200, "OK".to_string(), Some(data))
@@ -24,4 +50,51 @@ where
     pub fn err(code: i32, msg: String) -> Self {
         Self::new(code, msg, None)
     }
+    // PolicyErr 返回 302 和字段错误，LimitErr 返回 303/304，其余按 code 和 msg 返回
+    pub fn from_err<F>(code: i32, e: anyhow::Error, err_msg: F) -> Self
+    where
+        F: FnOnce(anyhow::Error) -> String,
+    {
+        let e = match e.downcast::<PolicyErr>() {
+            Ok(PolicyErr(errors)) => {
+                return Self {
+                    errors,
+                    ..Self::err(302, "invalid params".to_string())
+                }
+            }
+            Err(e) => e,
+        };
+        match e.downcast::<LimitErr>() {
+            Ok(e) => Self::err(e.code(), e.to_string()),
+            Err(e) => Self::err(code, err_msg(e)),
+        }
+    }
+}
+
+// 需要 into_make_service_with_connect_info 才能拿到 ip
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(addr) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .chars()
+            .take(256)
+            .collect();
+        Ok(ClientInfo {
+            ip: addr.ip(),
+            user_agent,
+        })
+    }
 }
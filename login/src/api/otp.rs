@@ -0,0 +1,47 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::Response;
+use crate::domain::otp::{self, OtpEnrollment};
+use crate::domain::trans_to_token::UserToken;
+
+pub async fn enroll(Extension(user): Extension<UserToken>) -> Json<Response<OtpEnrollment>> {
+    let resp = match otp::enroll(user.user_id, &user.user_name).await {
+        Ok(enrollment) => Response::ok(enrollment),
+        Err(e) => Response::from_err(301, e, |e| format!("err in enroll otp,{}", e)),
+    };
+    Json(resp)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ConfirmRequest {
+    pub code: String,
+}
+
+pub async fn confirm(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<ConfirmRequest>,
+) -> Json<Response<String>> {
+    let resp = match otp::confirm_enroll(user.user_id, &user.user_name, &request.code).await {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::from_err(301, e, |e| format!("err in confirm otp,{}", e)),
+    };
+    Json(resp)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AdminEnrollRequest {
+    pub user_name: String,
+}
+
+// 返回的 secret 和恢复码由 root 通过其他渠道交给用户
+pub async fn admin_enroll(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<AdminEnrollRequest>,
+) -> Json<Response<OtpEnrollment>> {
+    let resp = match otp::admin_enroll(&user.user_name, &request.user_name).await {
+        Ok(enrollment) => Response::ok(enrollment),
+        Err(e) => Response::from_err(301, e, |e| format!("err in enroll otp,{}", e)),
+    };
+    Json(resp)
+}
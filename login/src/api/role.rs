@@ -0,0 +1,115 @@
+use axum::extract::Query;
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::Response;
+use crate::domain::permission_cache::{CacheStats, PERMISSION_CACHE};
+use crate::domain::role_admin::{self, UrlPermission};
+use crate::domain::trans_to_token::UserToken;
+
+#[derive(Deserialize, Serialize)]
+pub struct RoleRequest {
+    pub role_name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UserRoleRequest {
+    pub user_name: String,
+    pub role_name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UrlRoleRequest {
+    // 为空时匹配所有方法
+    #[serde(default)]
+    pub method: String,
+    pub url: String,
+    pub role_name: String,
+}
+
+fn get_resp<T: Serialize>(result: anyhow::Result<T>, err_msg: &str) -> Json<Response<T>> {
+    Json(match result {
+        Ok(data) => Response::ok(data),
+        Err(e) => Response::err(301, format!("{},{}", err_msg, e)),
+    })
+}
+
+pub async fn create(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<RoleRequest>,
+) -> Json<Response<String>> {
+    let result = role_admin::create_role(&user.user_name, &request.role_name).await;
+    get_resp(result.map(|_| "ok".to_string()), "err in create role")
+}
+
+pub async fn delete(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<RoleRequest>,
+) -> Json<Response<String>> {
+    let result = role_admin::delete_role(&user.user_name, &request.role_name).await;
+    get_resp(result.map(|_| "ok".to_string()), "err in delete role")
+}
+
+pub async fn grant_user(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<UserRoleRequest>,
+) -> Json<Response<String>> {
+    let result =
+        role_admin::grant_user_role(&user.user_name, &request.user_name, &request.role_name).await;
+    get_resp(result.map(|_| "ok".to_string()), "err in grant role")
+}
+
+pub async fn revoke_user(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<UserRoleRequest>,
+) -> Json<Response<String>> {
+    let result =
+        role_admin::revoke_user_role(&user.user_name, &request.user_name, &request.role_name).await;
+    get_resp(result.map(|_| "ok".to_string()), "err in revoke role")
+}
+
+pub async fn grant_url(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<UrlRoleRequest>,
+) -> Json<Response<String>> {
+    let result = role_admin::grant_url(
+        &user.user_name,
+        &request.method,
+        &request.url,
+        &request.role_name,
+    )
+    .await;
+    get_resp(result.map(|_| "ok".to_string()), "err in grant url")
+}
+
+pub async fn revoke_url(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<UrlRoleRequest>,
+) -> Json<Response<String>> {
+    let result = role_admin::revoke_url(
+        &user.user_name,
+        &request.method,
+        &request.url,
+        &request.role_name,
+    )
+    .await;
+    get_resp(result.map(|_| "ok".to_string()), "err in revoke url")
+}
+
+pub async fn list() -> Json<Response<Vec<String>>> {
+    get_resp(role_admin::list_roles().await, "err in list roles")
+}
+
+pub async fn permissions(Query(request): Query<RoleRequest>) -> Json<Response<Vec<UrlPermission>>> {
+    let result = role_admin::list_permissions(&request.role_name).await;
+    get_resp(result, "err in list permissions")
+}
+
+pub async fn members(Query(request): Query<RoleRequest>) -> Json<Response<Vec<String>>> {
+    let result = role_admin::list_members(&request.role_name).await;
+    get_resp(result, "err in list members")
+}
+
+pub async fn cache_stats() -> Json<Response<CacheStats>> {
+    Json(Response::ok(PERMISSION_CACHE.stats()))
+}
@@ -0,0 +1,30 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::Response;
+use crate::domain::session::{self, Session};
+use crate::domain::trans_to_token::UserToken;
+
+pub async fn list(Extension(user): Extension<UserToken>) -> Json<Response<Vec<Session>>> {
+    let resp = match session::list(user.user_id, &user.sid).await {
+        Ok(sessions) => Response::ok(sessions),
+        Err(e) => Response::err(301, format!("err in list sessions,{}", e)),
+    };
+    Json(resp)
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevokeRequest {
+    pub session_id: String,
+}
+
+pub async fn revoke(
+    Extension(user): Extension<UserToken>,
+    Json(request): Json<RevokeRequest>,
+) -> Json<Response<String>> {
+    let resp = match session::revoke(user.user_id, &request.session_id).await {
+        Ok(_) => Response::ok("ok".to_string()),
+        Err(e) => Response::err(301, format!("err in revoke session,{}", e)),
+    };
+    Json(resp)
+}
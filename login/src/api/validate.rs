@@ -21,8 +21,14 @@ pub async fn auth<B>(mut req: Request<B>, next: Next<B>) -> Result<Response, Sta
         return Err(StatusCode::UNAUTHORIZED);
     };
 
-    if let Ok(current_user) = token_validate(auth_header) {
-        if auth_validate(current_user.user_id, req.uri().path()).await {
+    if let Ok(current_user) = token_validate(auth_header).await {
+        if auth_validate(
+            current_user.user_id,
+            req.method().as_str(),
+            req.uri().path(),
+        )
+        .await
+        {
             req.extensions_mut().insert(current_user);
             return Ok(next.run(req).await);
         }
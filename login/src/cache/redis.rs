@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::result;
 
 use chrono::Duration;
@@ -5,8 +6,75 @@ use lazy_static::lazy_static;
 use redis::AsyncCommands;
 use thiserror::Error;
 
+use crate::config::CONFIG;
+
 lazy_static! {
-    static ref REDIS_CLINET: redis::Client = redis::Client::open("redis://127.0.0.1/").unwrap();
+    static ref REDIS_CLINET: redis::Client =
+        redis::Client::open(CONFIG.redis.url.as_str()).expect("invalid redis url");
+    // 原子地消费 rt，被消费过的 rt 留下标记用于发现重放
+    static ref CONSUME_RT: redis::Script = redis::Script::new(
+        r"
+        local family = redis.call('GET', KEYS[1])
+        if family then
+            redis.call('DEL', KEYS[1])
+            redis.call('SET', KEYS[2], family, 'EX', ARGV[1])
+            return {1, family}
+        end
+        local used = redis.call('GET', KEYS[2])
+        if used then
+            return {2, used}
+        end
+        return {0, ''}
+        "
+    );
+    // 原子地检查锁定、退避并预留一次失败，check 为 0 时只记录已经发生的失败
+    // KEYS: lock, next, fail，ARGV: check, window, lockout, max_failures, 第 n 次失败后的退避秒数...
+    static ref LOGIN_ATTEMPT: redis::Script = redis::Script::new(
+        r"
+        if ARGV[1] == '1' then
+            local lock = redis.call('TTL', KEYS[1])
+            if lock > 0 then
+                return {1, lock}
+            end
+            local next = redis.call('TTL', KEYS[2])
+            if next > 0 then
+                return {2, next}
+            end
+        end
+        local failures = redis.call('INCR', KEYS[3])
+        redis.call('EXPIRE', KEYS[3], ARGV[2])
+        local max = tonumber(ARGV[4])
+        -- 预留的尝试还没有失败，超过上限才锁定
+        if failures > max or (ARGV[1] ~= '1' and failures >= max) then
+            redis.call('SET', KEYS[1], 1, 'EX', ARGV[3])
+            redis.call('DEL', KEYS[3])
+            return {3, failures}
+        end
+        local backoff = tonumber(ARGV[4 + failures] or '0')
+        if backoff > 0 then
+            redis.call('SET', KEYS[2], 1, 'EX', backoff)
+        end
+        return {0, failures}
+        "
+    );
+    // 撤回一次记录的失败
+    static ref RELEASE_LOGIN_ATTEMPT: redis::Script = redis::Script::new(
+        r"
+        if tonumber(redis.call('GET', KEYS[1]) or '0') > 0 then
+            return redis.call('DECR', KEYS[1])
+        end
+        return 0
+        "
+    );
+    // 验证码一致时删除，保证只能使用一次
+    static ref CONSUME_RESET_CODE: redis::Script = redis::Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('DEL', KEYS[1])
+        end
+        return 0
+        "
+    );
 }
 
 type Result<T> = result::Result<T, CacheErr>;
@@ -19,6 +87,9 @@ pub enum CacheErr {
     ExecErr(#[from] redis::RedisError),
 }
 
+// 角色、权限修改后通知所有 login 实例
+const PERMISSION_CHANNEL: &str = "permission_changed";
+
 async fn get_con() -> Result<redis::aio::Connection> {
     REDIS_CLINET
         .get_async_connection()
@@ -26,17 +97,386 @@ async fn get_con() -> Result<redis::aio::Connection> {
         .map_err(|e| CacheErr::GetConErr(e))
 }
 
-pub async fn set_rt_with_ttl(rt: &str, user_info: &str, timeout: Duration) -> Result<()> {
+// 用于 /readyz
+pub async fn ping() -> Result<()> {
+    redis::cmd("PING")
+        .query_async::<_, ()>(&mut get_con().await?)
+        .await?;
+    Ok(())
+}
+
+fn rt_key(rt: &str) -> String {
+    format!("rt:{}", rt)
+}
+
+fn rt_used_key(rt: &str) -> String {
+    format!("rt_used:{}", rt)
+}
+
+fn family_key(family: &str) -> String {
+    format!("rt_family:{}", family)
+}
+
+fn user_families_key(user_id: u64) -> String {
+    format!("rt_user:{}", user_id)
+}
+
+fn session_key(family: &str) -> String {
+    format!("rt_session:{}", family)
+}
+
+fn revoked_sid_key(sid: &str) -> String {
+    format!("revoked_sid:{}", sid)
+}
+
+fn revoked_jti_key(jti: &str) -> String {
+    format!("revoked_jti:{}", jti)
+}
+
+fn revoked_before_key(user_id: u64) -> String {
+    format!("revoked_before:{}", user_id)
+}
+
+fn reset_code_key(user_name: &str) -> String {
+    format!("reset_code:{}", user_name)
+}
+
+fn login_fail_key(subject: &str) -> String {
+    format!("login_fail:{}", subject)
+}
+
+fn login_next_key(subject: &str) -> String {
+    format!("login_next:{}", subject)
+}
+
+fn login_lock_key(subject: &str) -> String {
+    format!("login_lock:{}", subject)
+}
+
+fn otp_challenge_key(challenge: &str) -> String {
+    format!("otp_challenge:{}", challenge)
+}
+
+fn otp_challenge_fail_key(challenge: &str) -> String {
+    format!("otp_challenge_fail:{}", challenge)
+}
+
+fn otp_used_key(user_id: u64, code: &str) -> String {
+    format!("otp_used:{}:{}", user_id, code)
+}
+
+// rt -> family，family -> user_info，同一次登录之后刷新出来的 rt 属于同一个 family
+pub async fn set_rt_with_ttl(
+    rt: &str,
+    family: &str,
+    user_id: u64,
+    user_info: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let seconds = timeout.num_seconds() as usize;
+    redis::pipe()
+        .atomic()
+        .set_ex(rt_key(rt), family, seconds)
+        .ignore()
+        .set_ex(family_key(family), user_info, seconds)
+        .ignore()
+        .sadd(user_families_key(user_id), family)
+        .ignore()
+        .expire(user_families_key(user_id), seconds)
+        .ignore()
+        .query_async::<_, ()>(&mut get_con().await?)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConsumedRT {
+    Valid(String),
+    Reused(String),
+    NotFound,
+}
+
+pub async fn consume_rt(rt: &str, timeout: Duration) -> Result<ConsumedRT> {
+    let (status, family): (i64, String) = CONSUME_RT
+        .key(rt_key(rt))
+        .key(rt_used_key(rt))
+        .arg(timeout.num_seconds())
+        .invoke_async(&mut get_con().await?)
+        .await?;
+    Ok(match status {
+        1 => ConsumedRT::Valid(family),
+        2 => ConsumedRT::Reused(family),
+        _ => ConsumedRT::NotFound,
+    })
+}
+
+pub async fn get_user_by_family(family: &str) -> Result<Option<String>> {
+    Ok(get_con().await?.get(family_key(family)).await?)
+}
+
+// family 删除后，该 family 下仍未消费的 rt 也无法再刷新，已经签发的 token 在 timeout 内作废
+pub async fn revoke_family(family: &str, timeout: Duration) -> Result<()> {
+    redis::pipe()
+        .atomic()
+        .del(&[family_key(family), session_key(family)])
+        .ignore()
+        .set_ex(revoked_sid_key(family), 1, timeout.num_seconds() as usize)
+        .ignore()
+        .query_async::<_, ()>(&mut get_con().await?)
+        .await?;
+    Ok(())
+}
+
+// 一个 family 就是一次登录，记录设备信息，刷新时更新
+pub async fn set_session(family: &str, fields: &[(&str, String)], timeout: Duration) -> Result<()> {
+    redis::pipe()
+        .atomic()
+        .hset_multiple(session_key(family), fields)
+        .ignore()
+        .expire(session_key(family), timeout.num_seconds() as usize)
+        .ignore()
+        .query_async::<_, ()>(&mut get_con().await?)
+        .await?;
+    Ok(())
+}
+
+// 返回用户仍然有效的 session，顺便清理已经过期的 family
+pub async fn get_sessions(user_id: u64) -> Result<Vec<(String, HashMap<String, String>)>> {
+    let mut con = get_con().await?;
+    let families: Vec<String> = con.smembers(user_families_key(user_id)).await?;
+    if families.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut pipe = redis::pipe();
+    for family in &families {
+        pipe.hgetall(session_key(family));
+    }
+    let sessions: Vec<HashMap<String, String>> = pipe.query_async(&mut con).await?;
+    let (alive, expired): (Vec<_>, Vec<_>) = families
+        .into_iter()
+        .zip(sessions)
+        .partition(|(_, session)| !session.is_empty());
+    if !expired.is_empty() {
+        let expired: Vec<String> = expired.into_iter().map(|(family, _)| family).collect();
+        con.srem::<_, _, ()>(user_families_key(user_id), expired)
+            .await?;
+    }
+    Ok(alive)
+}
+
+// 作废一个 session，包括已经签发的 token，不属于该用户时返回 false
+pub async fn revoke_session(user_id: u64, family: &str, timeout: Duration) -> Result<bool> {
+    let mut con = get_con().await?;
+    if !con.sismember(user_families_key(user_id), family).await? {
+        return Ok(false);
+    }
+    redis::pipe()
+        .atomic()
+        .del(&[family_key(family), session_key(family)])
+        .ignore()
+        .srem(user_families_key(user_id), family)
+        .ignore()
+        .set_ex(revoked_sid_key(family), 1, timeout.num_seconds() as usize)
+        .ignore()
+        .query_async::<_, ()>(&mut con)
+        .await?;
+    Ok(true)
+}
+
+pub async fn revoke_jti(jti: &str, timeout: Duration) -> Result<()> {
+    // 已经过期的 token 不需要再记录
+    if timeout.num_seconds() <= 0 {
+        return Ok(());
+    }
+    get_con()
+        .await?
+        .set_ex::<_, _, ()>(revoked_jti_key(jti), 1, timeout.num_seconds() as usize)
+        .await?;
+    Ok(())
+}
+
+// 作废用户所有的 family，并且 before 之前签发的 token 都不再有效
+pub async fn revoke_user(user_id: u64, before: i64, timeout: Duration) -> Result<()> {
+    let mut con = get_con().await?;
+    let families: Vec<String> = con.smembers(user_families_key(user_id)).await?;
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for family in &families {
+        pipe.del(&[family_key(family), session_key(family)])
+            .ignore();
+    }
+    pipe.del(user_families_key(user_id))
+        .ignore()
+        .set_ex(
+            revoked_before_key(user_id),
+            before,
+            timeout.num_seconds() as usize,
+        )
+        .ignore()
+        .query_async::<_, ()>(&mut con)
+        .await?;
+    Ok(())
+}
+
+pub async fn is_revoked(jti: &str, sid: &str, user_id: u64, issued_at: i64) -> Result<bool> {
+    let (revoked, sid_revoked, before): (bool, bool, Option<i64>) = redis::pipe()
+        .exists(revoked_jti_key(jti))
+        .exists(revoked_sid_key(sid))
+        .get(revoked_before_key(user_id))
+        .query_async(&mut get_con().await?)
+        .await?;
+    Ok(revoked || sid_revoked || matches!(before, Some(before) if issued_at < before))
+}
+
+// 同一个用户只保留最新的验证码
+pub async fn set_reset_code(user_name: &str, code: &str, timeout: Duration) -> Result<()> {
     get_con()
         .await?
-        .set_ex(rt, user_info, timeout.num_seconds() as usize)
+        .set_ex::<_, _, ()>(
+            reset_code_key(user_name),
+            code,
+            timeout.num_seconds() as usize,
+        )
         .await?;
     Ok(())
 }
 
-pub async fn get_user_by_rt(rt: &str) -> Result<String> {
-    let str: String = get_con().await?.get(rt).await?;
-    Ok(str)
+pub async fn consume_reset_code(user_name: &str, code: &str) -> Result<bool> {
+    let deleted: i64 = CONSUME_RESET_CODE
+        .key(reset_code_key(user_name))
+        .arg(code)
+        .invoke_async(&mut get_con().await?)
+        .await?;
+    Ok(deleted == 1)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoginAttempt {
+    // 剩余的秒数
+    Locked(i64),
+    Backoff(i64),
+    // 记录后的失败次数，locked 表示这次达到了上限
+    Counted { failures: u32, locked: bool },
+}
+
+// 每次失败都会延长统计窗口，锁定后失败次数重新开始统计
+pub async fn record_login_attempt(
+    subject: &str,
+    check: bool,
+    window: Duration,
+    lockout: Duration,
+    max_failures: u32,
+    backoff_secs: &[u64],
+) -> Result<LoginAttempt> {
+    let mut invocation = LOGIN_ATTEMPT.prepare_invoke();
+    invocation
+        .key(login_lock_key(subject))
+        .key(login_next_key(subject))
+        .key(login_fail_key(subject))
+        .arg(check as i32)
+        .arg(window.num_seconds())
+        .arg(lockout.num_seconds())
+        .arg(max_failures);
+    for secs in backoff_secs {
+        invocation.arg(*secs);
+    }
+    let (status, value): (i64, i64) = invocation.invoke_async(&mut get_con().await?).await?;
+    Ok(match status {
+        1 => LoginAttempt::Locked(value),
+        2 => LoginAttempt::Backoff(value),
+        _ => LoginAttempt::Counted {
+            failures: value as u32,
+            locked: status == 3,
+        },
+    })
+}
+
+pub async fn release_login_attempt(subject: &str) -> Result<()> {
+    RELEASE_LOGIN_ATTEMPT
+        .key(login_fail_key(subject))
+        .invoke_async::<_, i64>(&mut get_con().await?)
+        .await?;
+    Ok(())
+}
+
+pub async fn clear_login_failure(subject: &str) -> Result<()> {
+    get_con()
+        .await?
+        .del::<_, ()>(&[
+            login_fail_key(subject),
+            login_next_key(subject),
+            login_lock_key(subject),
+        ])
+        .await?;
+    Ok(())
+}
+
+pub async fn set_otp_challenge(challenge: &str, user_name: &str, timeout: Duration) -> Result<()> {
+    get_con()
+        .await?
+        .set_ex::<_, _, ()>(
+            otp_challenge_key(challenge),
+            user_name,
+            timeout.num_seconds() as usize,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_otp_challenge(challenge: &str) -> Result<Option<String>> {
+    Ok(get_con().await?.get(otp_challenge_key(challenge)).await?)
+}
+
+pub async fn incr_otp_challenge_failure(challenge: &str, timeout: Duration) -> Result<u32> {
+    let (failures,): (u32,) = redis::pipe()
+        .atomic()
+        .incr(otp_challenge_fail_key(challenge), 1)
+        .expire(
+            otp_challenge_fail_key(challenge),
+            timeout.num_seconds() as usize,
+        )
+        .ignore()
+        .query_async(&mut get_con().await?)
+        .await?;
+    Ok(failures)
+}
+
+pub async fn del_otp_challenge(challenge: &str) -> Result<()> {
+    get_con()
+        .await?
+        .del::<_, ()>(&[
+            otp_challenge_key(challenge),
+            otp_challenge_fail_key(challenge),
+        ])
+        .await?;
+    Ok(())
+}
+
+// 同一个 code 在有效期内只能使用一次，已经使用过返回 false
+pub async fn mark_otp_used(user_id: u64, code: &str, timeout: Duration) -> Result<bool> {
+    let set: Option<String> = redis::cmd("SET")
+        .arg(otp_used_key(user_id, code))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(timeout.num_seconds())
+        .query_async(&mut get_con().await?)
+        .await?;
+    Ok(set.is_some())
+}
+
+pub async fn publish_permission_changed() -> Result<()> {
+    get_con()
+        .await?
+        .publish::<_, _, ()>(PERMISSION_CHANNEL, "")
+        .await?;
+    Ok(())
+}
+
+pub async fn subscribe_permission_changed() -> Result<redis::aio::PubSub> {
+    let mut pubsub = get_con().await?.into_pubsub();
+    pubsub.subscribe(PERMISSION_CHANNEL).await?;
+    Ok(pubsub)
 }
 
 #[cfg(test)]
@@ -48,12 +488,159 @@ mod tests {
     #[test]
     fn set_rt() {
         tokio_test::block_on(async {
-            set_rt_with_ttl("123", "233", Duration::seconds(1))
+            set_rt_with_ttl("123", "f123", 1, "233", Duration::seconds(1))
                 .await
                 .unwrap();
-            assert_eq!(get_user_by_rt("123").await.unwrap(), "233");
+            assert_eq!(
+                get_user_by_family("f123").await.unwrap(),
+                Some("233".to_string())
+            );
             sleep(std::time::Duration::new(1, 0));
-            println!("{:?}", get_user_by_rt("123").await)
+            println!("{:?}", get_user_by_family("f123").await)
+        })
+    }
+
+    #[test]
+    fn consume() {
+        tokio_test::block_on(async {
+            let ttl = Duration::seconds(10);
+            set_rt_with_ttl("456", "f456", 1, "233", ttl).await.unwrap();
+            assert_eq!(
+                consume_rt("456", ttl).await.unwrap(),
+                ConsumedRT::Valid("f456".to_string())
+            );
+            assert_eq!(
+                consume_rt("456", ttl).await.unwrap(),
+                ConsumedRT::Reused("f456".to_string())
+            );
+            assert_eq!(consume_rt("789", ttl).await.unwrap(), ConsumedRT::NotFound);
+
+            revoke_family("f456", ttl).await.unwrap();
+            assert_eq!(get_user_by_family("f456").await.unwrap(), None);
+            assert!(is_revoked("jti-456", "f456", 1, 100).await.unwrap());
+        })
+    }
+
+    #[test]
+    fn revoke() {
+        tokio_test::block_on(async {
+            let ttl = Duration::seconds(10);
+            revoke_jti("jti-1", ttl).await.unwrap();
+            assert!(is_revoked("jti-1", "", 2, 100).await.unwrap());
+            assert!(!is_revoked("jti-2", "", 2, 100).await.unwrap());
+
+            set_rt_with_ttl("rt-2", "f-2", 2, "233", ttl).await.unwrap();
+            revoke_user(2, 200, ttl).await.unwrap();
+            assert!(is_revoked("jti-2", "", 2, 100).await.unwrap());
+            assert!(!is_revoked("jti-2", "", 2, 200).await.unwrap());
+            assert_eq!(get_user_by_family("f-2").await.unwrap(), None);
+        })
+    }
+
+    #[test]
+    fn reset_code() {
+        tokio_test::block_on(async {
+            set_reset_code("333", "code", Duration::seconds(10))
+                .await
+                .unwrap();
+            assert!(!consume_reset_code("333", "other").await.unwrap());
+            assert!(consume_reset_code("333", "code").await.unwrap());
+            assert!(!consume_reset_code("333", "code").await.unwrap());
+        })
+    }
+
+    #[test]
+    fn login_failure() {
+        tokio_test::block_on(async {
+            let ttl = Duration::seconds(10);
+            let attempt = |check| record_login_attempt("user:test", check, ttl, ttl, 3, &[0, 5]);
+            clear_login_failure("user:test").await.unwrap();
+            assert_eq!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Counted {
+                    failures: 1,
+                    locked: false
+                }
+            );
+            release_login_attempt("user:test").await.unwrap();
+            assert_eq!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Counted {
+                    failures: 1,
+                    locked: false
+                }
+            );
+            // 第二次失败后退避，并发的尝试也会被拒绝
+            assert_eq!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Counted {
+                    failures: 2,
+                    locked: false
+                }
+            );
+            assert!(matches!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Backoff(secs) if secs > 0
+            ));
+            assert_eq!(
+                attempt(false).await.unwrap(),
+                LoginAttempt::Counted {
+                    failures: 3,
+                    locked: true
+                }
+            );
+            assert!(matches!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Locked(secs) if secs > 0
+            ));
+            clear_login_failure("user:test").await.unwrap();
+            assert!(matches!(
+                attempt(true).await.unwrap(),
+                LoginAttempt::Counted { failures: 1, .. }
+            ));
+            clear_login_failure("user:test").await.unwrap();
+        })
+    }
+
+    #[test]
+    fn otp() {
+        tokio_test::block_on(async {
+            let ttl = Duration::seconds(10);
+            set_otp_challenge("challenge", "333", ttl).await.unwrap();
+            assert_eq!(
+                get_otp_challenge("challenge").await.unwrap(),
+                Some("333".to_string())
+            );
+            assert_eq!(
+                incr_otp_challenge_failure("challenge", ttl).await.unwrap(),
+                1
+            );
+            del_otp_challenge("challenge").await.unwrap();
+            assert_eq!(get_otp_challenge("challenge").await.unwrap(), None);
+
+            assert!(mark_otp_used(1, "123456", ttl).await.unwrap());
+            assert!(!mark_otp_used(1, "123456", ttl).await.unwrap());
+        })
+    }
+
+    #[test]
+    fn session() {
+        tokio_test::block_on(async {
+            let ttl = Duration::seconds(10);
+            set_rt_with_ttl("rt-3", "f-3", 3, "233", ttl).await.unwrap();
+            set_session("f-3", &[("ip", "127.0.0.1".to_string())], ttl)
+                .await
+                .unwrap();
+            set_rt_with_ttl("rt-4", "f-4", 3, "233", ttl).await.unwrap();
+            let sessions = get_sessions(3).await.unwrap();
+            // f-4 没有 session 信息，会被清理
+            assert_eq!(sessions.len(), 1);
+            assert_eq!(sessions[0].0, "f-3");
+
+            assert!(!revoke_session(4, "f-3", ttl).await.unwrap());
+            assert!(revoke_session(3, "f-3", ttl).await.unwrap());
+            assert!(is_revoked("jti-3", "f-3", 3, 100).await.unwrap());
+            assert!(get_sessions(3).await.unwrap().is_empty());
         })
     }
 }
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::env;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local};
+use jsonwebtoken::Algorithm;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use util::config::{
+    check_addr, dev_secret, DatabaseConfig, RedisConfig, Validate, DEV_DATABASE_URL,
+};
+use util::log_init::init::LogConfig;
+use util::pb::tls::TlsFiles;
+use util::scope::Scope;
+use util::trace::TraceConfig;
+
+lazy_static! {
+    pub static ref CONFIG: Config = Config::load();
+}
+
+// 本地开发的 token secret，只在 dev profile 下接受
+const DEV_TOKEN_SECRET: &str = "local-dev-secret-do-not-use-in-deploy";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    #[serde(default)]
+    pub profile: Profile,
+    #[serde(default = "default_http_addr")]
+    pub http_addr: String,
+    #[serde(default = "default_grpc_addr")]
+    pub grpc_addr: String,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub redis: RedisConfig,
+    // 配置后 grpc 使用 mtls
+    pub tls: Option<TlsFiles>,
+    #[serde(default)]
+    pub token: TokenConfig,
+    #[serde(default)]
+    pub password: PasswordConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+    #[serde(default)]
+    pub otp: OtpConfig,
+    #[serde(default)]
+    pub permission_cache: PermissionCacheConfig,
+    // 角色对应的 scope，签发 token 时按用户生效的角色计算
+    #[serde(default)]
+    pub role_scopes: HashMap<String, Vec<Scope>>,
+    #[serde(default)]
+    pub clients: Vec<ClientConfig>,
+    #[serde(default)]
+    pub trace: TraceConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+// 默认按部署处理，secret 必须通过环境变量提供
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Deploy,
+    // 本地开发，没有配置的 secret 使用开发用的值
+    Dev,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenConfig {
+    #[serde(default = "default_issuer")]
+    pub issuer: String,
+    // 用于签发的 kid，其余的 key 只用于校验
+    pub current_kid: String,
+    pub keys: Vec<KeyConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyConfig {
+    pub kid: String,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    // HS* 使用，current_kid 的 secret 可以被环境变量 LOGIN_TOKEN_SECRET 覆盖
+    pub secret: Option<String>,
+    // RS*/ES*/EdDSA 使用，pem 文件路径，只用于校验的 key 可以不配置私钥
+    pub private_key: Option<String>,
+    pub public_key: Option<String>,
+    // 到期后不再接受该 key 签发的 token，也不再发布到 jwks
+    pub retire_at: Option<DateTime<Local>>,
+}
+
+// argon2id 的 cost 参数，修改后旧的 hash 会在用户下次登录时重新计算
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PasswordConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            memory_kib: argon2::Params::DEFAULT_M_COST,
+            iterations: argon2::Params::DEFAULT_T_COST,
+            parallelism: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+// 注册和修改密码时对用户名、密码的限制
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PolicyConfig {
+    pub user_name_min_len: usize,
+    pub user_name_max_len: usize,
+    // 除了字母数字之外允许出现在用户名中的字符
+    pub user_name_extra_chars: String,
+    pub reserved_names: Vec<String>,
+    pub password_min_len: usize,
+    pub password_max_len: usize,
+    // 小写、大写、数字、符号中至少包含几类
+    pub password_min_classes: usize,
+    pub password_min_entropy: f64,
+    // 每行一个，不区分大小写
+    pub banned_password_file: Option<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            user_name_min_len: 3,
+            user_name_max_len: 32,
+            user_name_extra_chars: "_-.".to_string(),
+            reserved_names: vec![
+                "root".to_string(),
+                "admin".to_string(),
+                "system".to_string(),
+            ],
+            password_min_len: 8,
+            password_max_len: 128,
+            password_min_classes: 2,
+            password_min_entropy: 40.0,
+            banned_password_file: None,
+        }
+    }
+}
+
+// 登录失败的限制，用户名按失败次数指数退避，用户名和 ip 失败过多时锁定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LockoutConfig {
+    pub user_max_failures: u32,
+    pub ip_max_failures: u32,
+    // 失败次数的统计窗口
+    pub window_secs: u64,
+    pub base_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub lockout_secs: u64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            user_max_failures: 5,
+            ip_max_failures: 50,
+            window_secs: 900,
+            base_backoff_secs: 1,
+            max_backoff_secs: 60,
+            lockout_secs: 900,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct OtpConfig {
+    // 显示在验证器 app 中的名称
+    pub issuer: String,
+    // 拥有这些角色的用户必须绑定 otp 才能登录
+    pub required_roles: Vec<String>,
+    pub challenge_secs: u64,
+    // 同一个 challenge 允许错误的次数
+    pub max_attempts: u32,
+    pub recovery_codes: usize,
+}
+
+impl Default for OtpConfig {
+    fn default() -> Self {
+        Self {
+            issuer: "supermarket".to_string(),
+            required_roles: vec!["root".to_string(), "worker".to_string()],
+            challenge_secs: 300,
+            max_attempts: 5,
+            recovery_codes: 10,
+        }
+    }
+}
+
+// 用户和 url 的角色缓存在内存中，修改时通过 redis 通知所有实例失效
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PermissionCacheConfig {
+    // 错过通知时最多使用这么久的旧数据
+    pub ttl_secs: u64,
+    pub max_users: usize,
+}
+
+impl Default for PermissionCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 60,
+            max_users: 10000,
+        }
+    }
+}
+
+// 服务之间调用使用 client credential 换取 token
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientConfig {
+    pub client_id: String,
+    // 不写在配置文件中，通过环境变量 LOGIN_CLIENT_<CLIENT_ID>_SECRET 设置
+    #[serde(default)]
+    pub secret: String,
+    pub scopes: Vec<Scope>,
+    // 通过 add_url_auth 可以注册的路由，以及这些路由可以绑定的角色
+    #[serde(default)]
+    pub routes: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default = "default_client_ttl")]
+    pub ttl_secs: i64,
+}
+
+fn default_client_ttl() -> i64 {
+    3600
+}
+
+// 重置密码的验证码通过 notifier 发送，本地使用 log 或者 file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotifyConfig {
+    #[default]
+    Log,
+    File {
+        path: String,
+    },
+}
+
+fn default_http_addr() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_grpc_addr() -> String {
+    "0.0.0.0:8089".to_string()
+}
+
+fn default_issuer() -> String {
+    "supermarket-login".to_string()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            profile: Default::default(),
+            http_addr: default_http_addr(),
+            grpc_addr: default_grpc_addr(),
+            database: Default::default(),
+            redis: Default::default(),
+            tls: None,
+            token: Default::default(),
+            password: Default::default(),
+            notify: Default::default(),
+            policy: Default::default(),
+            lockout: Default::default(),
+            otp: Default::default(),
+            permission_cache: Default::default(),
+            role_scopes: Default::default(),
+            clients: vec![],
+            trace: Default::default(),
+            log: Default::default(),
+        }
+    }
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            issuer: default_issuer(),
+            current_kid: String::new(),
+            keys: vec![],
+        }
+    }
+}
+
+impl Validate for Config {
+    fn validate(&self) -> anyhow::Result<()> {
+        check_addr("http_addr", &self.http_addr)?;
+        check_addr("grpc_addr", &self.grpc_addr)?;
+        self.database.validate()?;
+        self.redis.validate()?;
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+        if self.token.keys.is_empty() {
+            return Err(anyhow!("token.keys is required"));
+        }
+        let dev = self.profile == Profile::Dev;
+        if self.database.url == DEV_DATABASE_URL && !dev {
+            return Err(anyhow!(
+                "database.url uses the dev database outside the dev profile"
+            ));
+        }
+        for key in self.token.keys.iter().filter(|key| is_hmac(key.algorithm)) {
+            match key.secret.as_deref() {
+                None | Some("") => {
+                    return Err(anyhow!(
+                        "token.keys {} secret is required, set LOGIN_TOKEN_SECRET",
+                        key.kid
+                    ))
+                }
+                Some(DEV_TOKEN_SECRET) if !dev => {
+                    return Err(anyhow!(
+                        "token.keys {} uses the dev secret outside the dev profile",
+                        key.kid
+                    ))
+                }
+                _ => {}
+            }
+        }
+        for client in &self.clients {
+            let name = client_secret_var(&client.client_id);
+            if client.secret.is_empty() {
+                return Err(anyhow!(
+                    "clients {} secret is required, set {}",
+                    client.client_id,
+                    name
+                ));
+            }
+            if client.secret == dev_secret(&client.client_id) && !dev {
+                return Err(anyhow!(
+                    "clients {} uses the dev secret outside the dev profile",
+                    client.client_id
+                ));
+            }
+        }
+        self.trace.validate()?;
+        self.log.validate()?;
+        Ok(())
+    }
+}
+
+fn is_hmac(algorithm: Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    )
+}
+
+fn client_secret_var(client_id: &str) -> String {
+    format!("LOGIN_CLIENT_{}_SECRET", client_id.to_uppercase())
+}
+
+impl Config {
+    fn load() -> Self {
+        util::config::load_with("login", |config: &mut Config| {
+            config.set_secrets(|name| env::var(name).ok())
+        })
+    }
+
+    // keys 和 clients 是数组，secret 通过单独的环境变量设置
+    fn set_secrets(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let dev = self.profile == Profile::Dev;
+        if dev && self.database.url.is_empty() {
+            self.database.url = DEV_DATABASE_URL.to_string();
+        }
+        if let Some(secret) = var("LOGIN_TOKEN_SECRET") {
+            let current_kid = self.token.current_kid.clone();
+            self.token
+                .keys
+                .iter_mut()
+                .filter(|key| key.kid == current_kid)
+                .for_each(|key| key.secret = Some(secret.clone()));
+        }
+        if dev {
+            self.token
+                .keys
+                .iter_mut()
+                .filter(|key| is_hmac(key.algorithm) && key.secret.is_none())
+                .for_each(|key| key.secret = Some(DEV_TOKEN_SECRET.to_string()));
+        }
+        for client in self.clients.iter_mut() {
+            if let Some(secret) = var(&client_secret_var(&client.client_id)) {
+                client.secret = secret;
+            } else if dev && client.secret.is_empty() {
+                client.secret = dev_secret(&client.client_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            token: TokenConfig {
+                current_kid: "dev-hs256".to_string(),
+                keys: vec![KeyConfig {
+                    kid: "dev-hs256".to_string(),
+                    algorithm: Algorithm::HS256,
+                    secret: None,
+                    private_key: None,
+                    public_key: None,
+                    retire_at: None,
+                }],
+                ..Default::default()
+            },
+            clients: vec![ClientConfig {
+                client_id: "sale".to_string(),
+                secret: String::new(),
+                scopes: vec![],
+                routes: vec![],
+                roles: vec![],
+                ttl_secs: 3600,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn secrets() {
+        let mut config = config();
+        config.set_secrets(|_| None);
+        assert!(config.validate().is_err());
+
+        // dev profile 下使用开发用的值
+        config.profile = Profile::Dev;
+        config.set_secrets(|_| None);
+        assert!(config.validate().is_ok());
+        config.profile = Profile::Deploy;
+        assert!(config.validate().is_err());
+
+        config.set_secrets(|name| Some(format!("{}-from-env", name)));
+        assert!(config.validate().is_err());
+        config.database.url = "mysql://login:pass@db:3306/shop".to_string();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.clients[0].secret,
+            "LOGIN_CLIENT_SALE_SECRET-from-env"
+        );
+    }
+}
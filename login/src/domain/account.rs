@@ -0,0 +1,59 @@
+use anyhow::anyhow;
+use chrono::Duration;
+use tracing::{info, warn};
+
+use crate::cache::redis::{consume_reset_code, set_reset_code};
+use crate::domain::credential_policy::POLICY;
+use crate::domain::notify::NOTIFIER;
+use crate::domain::permission_cache;
+use crate::domain::trans_to_token::logout_all;
+use crate::repo::user::{delete_user, get_user_by_info, update_password, DBExecErr};
+
+// 修改密码后所有已签发的 token 都失效，需要重新登录
+pub async fn change_password(
+    user_name: &str,
+    old_pass_word: &str,
+    new_pass_word: &str,
+) -> anyhow::Result<()> {
+    POLICY.check_new_password(user_name, new_pass_word, "new_pass_word")?;
+    let mut user = get_user_by_info(user_name, Some(old_pass_word)).await?;
+    update_password(&mut user, new_pass_word).await?;
+    logout_all(user.id.unwrap_or(0)).await
+}
+
+pub async fn request_reset(user_name: &str) -> anyhow::Result<()> {
+    // 用户不存在也返回成功，避免探测用户名
+    match get_user_by_info(user_name, None).await {
+        Ok(_) => {}
+        Err(DBExecErr::UserNotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+    let code = uuid::Uuid::new_v4().simple().to_string();
+    set_reset_code(user_name, &code, Duration::minutes(15)).await?;
+    NOTIFIER.notify(user_name, "reset password", &code).await
+}
+
+pub async fn reset_password(
+    user_name: &str,
+    code: &str,
+    new_pass_word: &str,
+) -> anyhow::Result<()> {
+    POLICY.check_new_password(user_name, new_pass_word, "new_pass_word")?;
+    if !consume_reset_code(user_name, code).await? {
+        warn!(target: "security", "invalid reset code for {}", user_name);
+        return Err(anyhow!("invalid reset code"));
+    }
+    let mut user = get_user_by_info(user_name, None).await?;
+    update_password(&mut user, new_pass_word).await?;
+    logout_all(user.id.unwrap_or(0)).await
+}
+
+pub async fn delete_account(operator: &str, user_name: &str) -> anyhow::Result<()> {
+    let user = get_user_by_info(user_name, None).await?;
+    let user_id = user.id.unwrap_or(0);
+    delete_user(user_id).await?;
+    logout_all(user_id).await?;
+    permission_cache::changed().await;
+    info!(target: "security", "{} delete account {}", operator, user_name);
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::config::{PolicyConfig, CONFIG};
+
+lazy_static! {
+    pub static ref POLICY: CredentialPolicy =
+        CredentialPolicy::from_config(&CONFIG.policy).expect("load credential policy err");
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub msg: String,
+}
+
+impl FieldError {
+    fn new(field: &str, code: &str, msg: String) -> Self {
+        Self {
+            field: field.to_string(),
+            code: code.to_string(),
+            msg,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub struct PolicyErr(pub Vec<FieldError>);
+
+impl Display for PolicyErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "policy err {:?}", self.0)
+    }
+}
+
+pub struct CredentialPolicy {
+    config: PolicyConfig,
+    banned_passwords: HashSet<String>,
+}
+
+impl CredentialPolicy {
+    pub fn from_config(config: &PolicyConfig) -> anyhow::Result<Self> {
+        let banned_passwords = match &config.banned_password_file {
+            None => HashSet::new(),
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("read banned password file err,{},{}", path, e))?
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty())
+                .collect(),
+        };
+        Ok(Self {
+            config: config.clone(),
+            banned_passwords,
+        })
+    }
+
+    pub fn check_sign(&self, user_name: &str, pass_word: &str) -> Result<(), PolicyErr> {
+        let mut errors = self.check_user_name(user_name);
+        errors.extend(self.check_password(user_name, pass_word, "pass_word"));
+        to_result(errors)
+    }
+
+    pub fn check_new_password(
+        &self,
+        user_name: &str,
+        pass_word: &str,
+        field: &str,
+    ) -> Result<(), PolicyErr> {
+        to_result(self.check_password(user_name, pass_word, field))
+    }
+
+    fn check_user_name(&self, user_name: &str) -> Vec<FieldError> {
+        let field = "user_name";
+        let config = &self.config;
+        let mut errors = vec![];
+        let len = user_name.chars().count();
+        if len < config.user_name_min_len || len > config.user_name_max_len {
+            errors.push(FieldError::new(
+                field,
+                "length",
+                format!(
+                    "length must between {} and {}",
+                    config.user_name_min_len, config.user_name_max_len
+                ),
+            ));
+            // 过长的输入不再做后面的检查
+            if len > config.user_name_max_len {
+                return errors;
+            }
+        }
+        if !user_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || config.user_name_extra_chars.contains(c))
+        {
+            errors.push(FieldError::new(
+                field,
+                "charset",
+                format!(
+                    "only letters, digits and {} are allowed",
+                    config.user_name_extra_chars
+                ),
+            ));
+        }
+        if config
+            .reserved_names
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(user_name))
+        {
+            errors.push(FieldError::new(
+                field,
+                "reserved",
+                format!("{} is reserved", user_name),
+            ));
+        }
+        errors
+    }
+
+    fn check_password(&self, user_name: &str, pass_word: &str, field: &str) -> Vec<FieldError> {
+        let config = &self.config;
+        let mut errors = vec![];
+        let len = pass_word.chars().count();
+        if len < config.password_min_len || len > config.password_max_len {
+            errors.push(FieldError::new(
+                field,
+                "length",
+                format!(
+                    "length must between {} and {}",
+                    config.password_min_len, config.password_max_len
+                ),
+            ));
+            if len > config.password_max_len {
+                return errors;
+            }
+        }
+        let lower = pass_word.to_lowercase();
+        if self.banned_passwords.contains(&lower) {
+            errors.push(FieldError::new(
+                field,
+                "banned",
+                "password is too common".to_string(),
+            ));
+        }
+        if !user_name.is_empty() && lower.contains(&user_name.to_lowercase()) {
+            errors.push(FieldError::new(
+                field,
+                "contains_user_name",
+                "password must not contain user name".to_string(),
+            ));
+        }
+        let (classes, _) = char_classes(pass_word);
+        if classes < config.password_min_classes {
+            errors.push(FieldError::new(
+                field,
+                "classes",
+                format!(
+                    "need at least {} of lowercase, uppercase, digit and symbol",
+                    config.password_min_classes
+                ),
+            ));
+        }
+        if entropy(pass_word) < config.password_min_entropy {
+            errors.push(FieldError::new(
+                field,
+                "entropy",
+                "password is too simple".to_string(),
+            ));
+        }
+        errors
+    }
+}
+
+fn to_result(errors: Vec<FieldError>) -> Result<(), PolicyErr> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(PolicyErr(errors))
+}
+
+type IsClass = fn(&char) -> bool;
+
+// 返回包含的字符类别数量和对应的字符集大小
+fn char_classes(pass_word: &str) -> (usize, usize) {
+    let classes: [(IsClass, usize); 4] = [
+        (char::is_ascii_lowercase, 26),
+        (char::is_ascii_uppercase, 26),
+        (char::is_ascii_digit, 10),
+        (|c| !c.is_ascii_alphanumeric(), 33),
+    ];
+    classes
+        .iter()
+        .filter(|(is_class, _)| pass_word.chars().any(|c| is_class(&c)))
+        .fold((0, 0), |(count, pool), (_, size)| (count + 1, pool + size))
+}
+
+// 重复的字符不增加熵，按 不同字符数 * log2(字符集大小) 估算
+fn entropy(pass_word: &str) -> f64 {
+    let (_, pool) = char_classes(pass_word);
+    if pool == 0 {
+        return 0.0;
+    }
+    let distinct = pass_word.chars().collect::<HashSet<_>>().len();
+    distinct as f64 * (pool as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(result: Result<(), PolicyErr>) -> Vec<String> {
+        match result {
+            Ok(_) => vec![],
+            Err(PolicyErr(errors)) => errors.into_iter().map(|e| e.code).collect(),
+        }
+    }
+
+    #[test]
+    fn user_name() {
+        let policy = CredentialPolicy::from_config(&PolicyConfig::default()).unwrap();
+        assert_eq!(
+            codes(policy.check_sign("worker_1", "Gr4pe-Soda")),
+            Vec::<String>::new()
+        );
+        assert_eq!(codes(policy.check_sign("", "Gr4pe-Soda")), vec!["length"]);
+        assert_eq!(
+            codes(policy.check_sign("a b", "Gr4pe-Soda")),
+            vec!["charset"]
+        );
+        assert_eq!(
+            codes(policy.check_sign("Root", "Gr4pe-Soda")),
+            vec!["reserved"]
+        );
+        assert_eq!(
+            codes(policy.check_sign(&"a".repeat(1 << 20), "Gr4pe-Soda")),
+            vec!["length"]
+        );
+    }
+
+    #[test]
+    fn password() {
+        let dir = std::env::temp_dir().join("policy_banned_passwords.txt");
+        fs::write(&dir, "password1\n\n").unwrap();
+        let policy = CredentialPolicy::from_config(&PolicyConfig {
+            banned_password_file: Some(dir.to_string_lossy().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let check = |pass_word: &str| codes(policy.check_new_password("worker", pass_word, "p"));
+        assert_eq!(check("Gr4pe-Soda"), Vec::<String>::new());
+        assert_eq!(check("Sh0rt-7"), vec!["length"]);
+        assert_eq!(check("PassWord1"), vec!["banned"]);
+        assert_eq!(check("worker2023"), vec!["contains_user_name"]);
+        assert_eq!(check("abcdefghij"), vec!["classes"]);
+        assert_eq!(check("aaaaaaaa11"), vec!["entropy"]);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+// job 的 url 是路由模式：
+//   /item/list       完全匹配
+//   /item/{item_id}  匹配一段，* 同理
+//   /static/**       前缀匹配，只能放在最后
+// method 为空表示匹配所有方法，路径相同时有具体 method 的优先
+#[derive(Default, Debug)]
+pub struct JobRouter {
+    root: Node,
+}
+
+#[derive(Default, Debug)]
+struct Node {
+    statics: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    // (method, job_id)
+    jobs: Vec<(String, u64)>,
+    catch_all: Vec<(String, u64)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Static(&'a str),
+    Param,
+    CatchAll,
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn parse(pattern: &str) -> anyhow::Result<Vec<Segment<'_>>> {
+    let parts: Vec<&str> = segments(pattern).collect();
+    let mut result = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let segment = match *part {
+            "**" if i + 1 == parts.len() => Segment::CatchAll,
+            "**" => return Err(anyhow!("`**` must be the last segment of {}", pattern)),
+            "*" => Segment::Param,
+            _ if part.starts_with('{') && part.ends_with('}') && part.len() > 2 => Segment::Param,
+            _ if part.contains(['{', '}', '*']) => {
+                return Err(anyhow!("invalid segment `{}` in {}", part, pattern))
+            }
+            _ => Segment::Static(part),
+        };
+        result.push(segment);
+    }
+    Ok(result)
+}
+
+pub fn normalize_method(method: &str) -> String {
+    match method.trim() {
+        "*" => String::new(),
+        method => method.to_ascii_uppercase(),
+    }
+}
+
+pub fn check_pattern(pattern: &str) -> anyhow::Result<()> {
+    parse(pattern).map(|_| ())
+}
+
+fn upsert(jobs: &mut Vec<(String, u64)>, method: String, job_id: u64) {
+    match jobs.iter_mut().find(|(m, _)| *m == method) {
+        Some(job) => job.1 = job_id,
+        None => jobs.push((method, job_id)),
+    }
+}
+
+fn pick(jobs: &[(String, u64)], method: &str) -> Option<u64> {
+    jobs.iter()
+        .find(|(m, _)| m == method)
+        .or_else(|| jobs.iter().find(|(m, _)| m.is_empty()))
+        .map(|(_, job_id)| *job_id)
+}
+
+impl Node {
+    // 优先级 静态 > 参数 > 前缀，走不通时回溯
+    fn find(&self, path: &[&str], method: &str) -> Option<u64> {
+        if let Some((first, rest)) = path.split_first() {
+            let job = self.statics.get(*first).and_then(|n| n.find(rest, method));
+            if job.is_some() {
+                return job;
+            }
+            let job = self.param.as_ref().and_then(|n| n.find(rest, method));
+            if job.is_some() {
+                return job;
+            }
+        } else if let Some(job) = pick(&self.jobs, method) {
+            return Some(job);
+        }
+        pick(&self.catch_all, method)
+    }
+}
+
+impl JobRouter {
+    pub fn insert(&mut self, method: &str, pattern: &str, job_id: u64) -> anyhow::Result<()> {
+        let mut node = &mut self.root;
+        for segment in parse(pattern)? {
+            node = match segment {
+                Segment::Static(part) => node.statics.entry(part.to_string()).or_default(),
+                Segment::Param => node.param.get_or_insert_with(Default::default),
+                Segment::CatchAll => {
+                    upsert(&mut node.catch_all, normalize_method(method), job_id);
+                    return Ok(());
+                }
+            };
+        }
+        upsert(&mut node.jobs, normalize_method(method), job_id);
+        Ok(())
+    }
+
+    pub fn find(&self, method: &str, path: &str) -> Option<u64> {
+        let path: Vec<&str> = segments(path).collect();
+        self.root.find(&path, &normalize_method(method))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern() {
+        assert_eq!(
+            parse("/comment/{item_id}/*/**").unwrap(),
+            vec![
+                Segment::Static("comment"),
+                Segment::Param,
+                Segment::Param,
+                Segment::CatchAll
+            ]
+        );
+        assert!(parse("/static/**/x").is_err());
+        assert!(parse("/item/{}").is_err());
+        assert!(parse("/item/a*").is_err());
+    }
+
+    #[test]
+    fn find() {
+        let mut router = JobRouter::default();
+        router.insert("", "/comment_of", 1).unwrap();
+        router.insert("post", "/item/{item_id}", 2).unwrap();
+        router.insert("GET", "/item/{item_id}", 3).unwrap();
+        router.insert("GET", "/item/list", 4).unwrap();
+        router.insert("*", "/static/**", 5).unwrap();
+        router.insert("DELETE", "/static/{file}", 6).unwrap();
+
+        assert_eq!(router.find("GET", "/comment_of"), Some(1));
+        assert_eq!(router.find("POST", "/comment_of/"), Some(1));
+        assert_eq!(router.find("POST", "/item/3"), Some(2));
+        assert_eq!(router.find("GET", "/item/3"), Some(3));
+        assert_eq!(router.find("DELETE", "/item/3"), None);
+        assert_eq!(router.find("GET", "/item/list"), Some(4));
+        // 静态段的 method 不匹配时回溯到参数
+        assert_eq!(router.find("POST", "/item/list"), Some(2));
+        assert_eq!(router.find("GET", "/static"), Some(5));
+        assert_eq!(router.find("GET", "/static/a/b.js"), Some(5));
+        assert_eq!(router.find("DELETE", "/static/a"), Some(6));
+        assert_eq!(router.find("GET", "/static/a"), Some(5));
+        assert_eq!(router.find("GET", "/item"), None);
+        assert_eq!(router.find("GET", "/"), None);
+    }
+}
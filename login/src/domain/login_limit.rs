@@ -0,0 +1,150 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+
+use chrono::Duration;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::cache::redis::{
+    clear_login_failure, record_login_attempt, release_login_attempt, LoginAttempt,
+};
+use crate::config::{LockoutConfig, CONFIG};
+
+#[derive(Debug, Error)]
+pub enum LimitErr {
+    Locked(i64),
+    Backoff(i64),
+}
+
+impl LimitErr {
+    pub fn code(&self) -> i32 {
+        match self {
+            LimitErr::Locked(_) => 303,
+            LimitErr::Backoff(_) => 304,
+        }
+    }
+}
+
+impl Display for LimitErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitErr::Locked(secs) => write!(f, "account locked, retry after {}s", secs),
+            LimitErr::Backoff(secs) => write!(f, "too many failures, retry after {}s", secs),
+        }
+    }
+}
+
+fn user_subject(user_name: &str) -> String {
+    format!("user:{}", user_name)
+}
+
+fn ip_subject(ip: IpAddr) -> String {
+    format!("ip:{}", ip)
+}
+
+// 校验密码之前先原子地检查并记录一次失败，并发的尝试不能绕过退避，成功后再撤回
+pub async fn reserve(user_name: &str, ip: IpAddr) -> anyhow::Result<()> {
+    let config = &CONFIG.lockout;
+    // ip 只锁定不退避，避免同一出口的其他用户被拖慢
+    let ip_subject = ip_subject(ip);
+    attempt(&ip_subject, true, config.ip_max_failures, &[]).await?;
+    let result = attempt(
+        &user_subject(user_name),
+        true,
+        config.user_max_failures,
+        &user_backoffs(config),
+    )
+    .await;
+    // 用户被拒绝时没有校验密码，不计入 ip 的失败
+    if result.is_err() {
+        release_login_attempt(&ip_subject).await?;
+    }
+    result
+}
+
+// 不经过 reserve 的失败，例如 otp 验证码错误
+pub async fn record_failure(user_name: &str, ip: IpAddr) -> anyhow::Result<()> {
+    let config = &CONFIG.lockout;
+    attempt(&ip_subject(ip), false, config.ip_max_failures, &[]).await?;
+    attempt(
+        &user_subject(user_name),
+        false,
+        config.user_max_failures,
+        &user_backoffs(config),
+    )
+    .await
+}
+
+pub async fn record_success(user_name: &str, ip: IpAddr) -> anyhow::Result<()> {
+    clear_login_failure(&user_subject(user_name)).await?;
+    release_login_attempt(&ip_subject(ip)).await?;
+    Ok(())
+}
+
+// 不指定 ip 时 ip 的锁定保留到 lockout_secs 过期
+pub async fn unlock(user_name: &str, ip: Option<IpAddr>) -> anyhow::Result<()> {
+    clear_login_failure(&user_subject(user_name)).await?;
+    if let Some(ip) = ip {
+        clear_login_failure(&ip_subject(ip)).await?;
+    }
+    Ok(())
+}
+
+async fn attempt(
+    subject: &str,
+    check: bool,
+    max_failures: u32,
+    backoff: &[u64],
+) -> anyhow::Result<()> {
+    let config = &CONFIG.lockout;
+    let window = Duration::seconds(config.window_secs as i64);
+    let lockout = Duration::seconds(config.lockout_secs as i64);
+    match record_login_attempt(subject, check, window, lockout, max_failures, backoff).await? {
+        LoginAttempt::Locked(secs) => Err(LimitErr::Locked(secs).into()),
+        LoginAttempt::Backoff(secs) => Err(LimitErr::Backoff(secs).into()),
+        LoginAttempt::Counted { failures, locked } => {
+            if locked {
+                warn!(target: "security", "lock {} after {} failures", subject, failures);
+                if check {
+                    return Err(LimitErr::Locked(config.lockout_secs as i64).into());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+// 第 n 次失败后的退避秒数，n 从 1 开始
+fn user_backoffs(config: &LockoutConfig) -> Vec<u64> {
+    (1..config.user_max_failures)
+        .map(|n| backoff_secs(config, n))
+        .collect()
+}
+
+fn backoff_secs(config: &LockoutConfig, failures: u32) -> u64 {
+    if failures == 0 {
+        return 0;
+    }
+    let exp = (failures - 1).min(32);
+    config
+        .base_backoff_secs
+        .saturating_mul(1 << exp)
+        .min(config.max_backoff_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let config = LockoutConfig::default();
+        let secs: Vec<u64> = (0..9).map(|n| backoff_secs(&config, n)).collect();
+        assert_eq!(secs, vec![0, 1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(backoff_secs(&config, u32::MAX), 60);
+        assert_eq!(
+            user_backoffs(&config).len() as u32,
+            config.user_max_failures - 1
+        );
+    }
+}
@@ -1,2 +1,13 @@
+pub mod account;
+pub mod credential_policy;
+pub mod job_router;
+pub mod key_ring;
+pub mod login_limit;
+pub mod notify;
+pub mod otp;
+pub mod permission_cache;
+pub mod role_admin;
+pub mod scope;
+pub mod session;
 pub mod trans_to_token;
 pub mod validate_auth;
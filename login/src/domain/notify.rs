@@ -0,0 +1,77 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use async_trait::async_trait;
+use chrono::Local;
+use lazy_static::lazy_static;
+use tracing::info;
+
+use crate::config::{NotifyConfig, CONFIG};
+
+lazy_static! {
+    pub static ref NOTIFIER: Box<dyn Notifier> = from_config(&CONFIG.notify);
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, user_name: &str, subject: &str, content: &str) -> anyhow::Result<()>;
+}
+
+fn from_config(config: &NotifyConfig) -> Box<dyn Notifier> {
+    match config {
+        NotifyConfig::Log => Box::new(LogNotifier),
+        NotifyConfig::File { path } => Box::new(FileNotifier { path: path.clone() }),
+    }
+}
+
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, user_name: &str, subject: &str, content: &str) -> anyhow::Result<()> {
+        info!(target: "notify", "to {},{}: {}", user_name, subject, content);
+        Ok(())
+    }
+}
+
+pub struct FileNotifier {
+    pub path: String,
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn notify(&self, user_name: &str, subject: &str, content: &str) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "{} to {},{}: {}",
+            Local::now().to_rfc3339(),
+            user_name,
+            subject,
+            content
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_notify() {
+        let path = std::env::temp_dir().join("login_notify_test.log");
+        let _ = std::fs::remove_file(&path);
+        let notifier = FileNotifier {
+            path: path.to_string_lossy().to_string(),
+        };
+        tokio_test::block_on(async {
+            notifier.notify("333", "reset", "code").await.unwrap();
+        });
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("to 333,reset: code"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::net::IpAddr;
+
+use anyhow::anyhow;
+use chrono::{Duration, Local};
+use serde::Serialize;
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::{info, warn};
+
+use crate::cache::redis::{
+    del_otp_challenge, get_otp_challenge, incr_otp_challenge_failure, mark_otp_used,
+    set_otp_challenge,
+};
+use crate::config::CONFIG;
+use crate::domain::login_limit;
+use crate::repo::auth::Role;
+use crate::repo::otp::{delete_recovery_code, get_recovery_codes, replace_otp, UserOtp};
+use crate::repo::user::{get_user_by_info, hash_secret, verify_secret, User};
+
+#[derive(Serialize, Debug)]
+pub struct OtpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+    // 只在绑定时返回一次
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OtpChallenge {
+    pub challenge_token: String,
+    pub expires_in: u64,
+}
+
+fn totp(issuer: &str, user_name: &str, secret: &str) -> anyhow::Result<TOTP> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| anyhow!("invalid otp secret,{:?}", e))?;
+    Ok(TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(issuer.to_string()),
+        user_name.to_string(),
+    )?)
+}
+
+fn new_recovery_code() -> String {
+    let code = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", &code[..5], &code[5..10])
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == 6 && code.chars().all(|c| c.is_ascii_digit())
+}
+
+async fn otp_required(user_id: u64) -> bool {
+    let roles = Role::get_by_user(user_id).await;
+    CONFIG
+        .otp
+        .required_roles
+        .iter()
+        .any(|role| roles.contains(role))
+}
+
+// 生成新的 secret 和恢复码，确认之前不生效
+pub async fn start_enroll(user_id: u64, user_name: &str) -> anyhow::Result<OtpEnrollment> {
+    let secret = Secret::generate_secret().to_encoded().to_string();
+    let provisioning_uri = totp(&CONFIG.otp.issuer, user_name, &secret)?.get_url();
+    let recovery_codes: Vec<String> = (0..CONFIG.otp.recovery_codes)
+        .map(|_| new_recovery_code())
+        .collect();
+    let code_hashes = recovery_codes
+        .iter()
+        .map(|code| hash_secret(code))
+        .collect::<Result<Vec<_>, _>>()?;
+    replace_otp(&mut UserOtp::new(user_id, secret.clone()), code_hashes).await?;
+    Ok(OtpEnrollment {
+        secret,
+        provisioning_uri,
+        recovery_codes,
+    })
+}
+
+pub async fn enroll(user_id: u64, user_name: &str) -> anyhow::Result<OtpEnrollment> {
+    if let Some(otp) = UserOtp::get_by_user(user_id).await? {
+        if otp.confirmed() {
+            return Err(anyhow!("otp already enabled"));
+        }
+    }
+    start_enroll(user_id, user_name).await
+}
+
+// root 为需要 otp 的用户发放绑定，通过其他渠道交给用户，用户登录时第一次输入正确的 code 完成绑定
+pub async fn admin_enroll(operator: &str, user_name: &str) -> anyhow::Result<OtpEnrollment> {
+    let user = get_user_by_info(user_name, None).await?;
+    let enrollment = enroll(user.id.unwrap_or(0), user_name).await?;
+    info!(target: "security", "{} issue otp enrollment for {}", operator, user_name);
+    Ok(enrollment)
+}
+
+pub async fn confirm_enroll(user_id: u64, user_name: &str, code: &str) -> anyhow::Result<()> {
+    let mut otp = UserOtp::get_by_user(user_id)
+        .await?
+        .ok_or(anyhow!("otp not enrolled"))?;
+    if otp.confirmed() {
+        return Err(anyhow!("otp already enabled"));
+    }
+    if !check_totp(&otp, user_name, code).await? {
+        return Err(anyhow!("invalid otp code"));
+    }
+    otp.confirm(Local::now().timestamp()).await?;
+    Ok(())
+}
+
+async fn check_totp(otp: &UserOtp, user_name: &str, code: &str) -> anyhow::Result<bool> {
+    if !is_totp_code(code)
+        || !totp(&CONFIG.otp.issuer, user_name, &otp.secret)?.check_current(code)?
+    {
+        return Ok(false);
+    }
+    // 允许前后一个周期，90 秒之内同一个 code 不能重复使用
+    Ok(mark_otp_used(otp.user_id, code, Duration::seconds(90)).await?)
+}
+
+async fn check_recovery_code(user_id: u64, code: &str) -> anyhow::Result<bool> {
+    for recovery in get_recovery_codes(user_id).await? {
+        if verify_secret(code, &recovery.code_hash) {
+            // 并发使用同一个恢复码时只有一个能删除成功
+            return Ok(delete_recovery_code(recovery.id.unwrap_or(0)).await?);
+        }
+    }
+    Ok(false)
+}
+
+// 用户需要第二步验证时返回 challenge
+// 登录时不生成绑定，否则只知道密码的人就能绑定自己的设备，需要 otp 但没有绑定时拒绝登录
+pub async fn login_challenge(user: &User) -> anyhow::Result<Option<OtpChallenge>> {
+    let user_id = user.id.unwrap_or(0);
+    let otp = UserOtp::get_by_user(user_id).await?;
+    if !otp.as_ref().is_some_and(UserOtp::confirmed) {
+        if !otp_required(user_id).await {
+            return Ok(None);
+        }
+        // 已经发放但未确认的绑定沿用原来的 secret，在 verify_challenge 中确认
+        if otp.is_none() {
+            return Err(anyhow!("otp required, ask root to issue an enrollment"));
+        }
+    }
+    let challenge_token = uuid::Uuid::new_v4().to_string();
+    let expires_in = CONFIG.otp.challenge_secs;
+    set_otp_challenge(
+        &challenge_token,
+        &user.user_name,
+        Duration::seconds(expires_in as i64),
+    )
+    .await?;
+    Ok(Some(OtpChallenge {
+        challenge_token,
+        expires_in,
+    }))
+}
+
+// 校验成功返回 challenge 对应的用户，challenge 只能使用一次
+pub async fn verify_challenge(challenge: &str, code: &str, ip: IpAddr) -> anyhow::Result<User> {
+    let user_name = get_otp_challenge(challenge)
+        .await?
+        .ok_or(anyhow!("invalid challenge token"))?;
+    let user = get_user_by_info(&user_name, None).await?;
+    let user_id = user.id.unwrap_or(0);
+    let mut otp = UserOtp::get_by_user(user_id)
+        .await?
+        .ok_or(anyhow!("otp not enrolled"))?;
+    let ok = if otp.confirmed() {
+        check_totp(&otp, &user_name, code).await? || check_recovery_code(user_id, code).await?
+    } else if check_totp(&otp, &user_name, code).await? {
+        otp.confirm(Local::now().timestamp()).await?;
+        true
+    } else {
+        false
+    };
+    if !ok {
+        warn!(target: "security", "invalid otp code for {}", user_name);
+        login_limit::record_failure(&user_name, ip).await?;
+        let timeout = Duration::seconds(CONFIG.otp.challenge_secs as i64);
+        if incr_otp_challenge_failure(challenge, timeout).await? >= CONFIG.otp.max_attempts {
+            del_otp_challenge(challenge).await?;
+        }
+        return Err(anyhow!("invalid otp code"));
+    }
+    del_otp_challenge(challenge).await?;
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totp_code() {
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let otp = totp("supermarket", "worker", &secret).unwrap();
+        assert!(otp
+            .get_url()
+            .starts_with("otpauth://totp/supermarket:worker?secret="));
+        let code = otp.generate(1_000_000);
+        assert!(is_totp_code(&code));
+        assert!(otp.check(&code, 1_000_000 + 30));
+        assert!(!otp.check(&code, 1_000_000 + 90));
+        assert!(totp("supermarket", "a:b", &secret).is_err());
+    }
+
+    #[test]
+    fn recovery_code() {
+        let code = new_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert!(!is_totp_code(&code));
+    }
+}
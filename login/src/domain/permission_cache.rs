@@ -0,0 +1,319 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::cache::redis::{publish_permission_changed, subscribe_permission_changed};
+use crate::config::CONFIG;
+use crate::domain::validate_auth;
+use crate::repo::auth::{
+    inherited_roles, Role, RoleBindJob, RoleParent, UserBindRole, DEFAULT_ROLE,
+};
+
+lazy_static! {
+    pub static ref PERMISSION_CACHE: PermissionCache = PermissionCache::new(
+        Duration::from_secs(CONFIG.permission_cache.ttl_secs),
+        CONFIG.permission_cache.max_users,
+    );
+}
+
+// 角色、继承关系和 url 的绑定数量少，一次全部加载
+struct Matrix {
+    role_names: HashMap<u64, String>,
+    edges: Vec<RoleParent>,
+    job_roles: HashMap<u64, HashSet<String>>,
+}
+
+impl Matrix {
+    fn new(roles: Vec<Role>, edges: Vec<RoleParent>, role_jobs: Vec<RoleBindJob>) -> Self {
+        let role_names: HashMap<u64, String> = roles
+            .into_iter()
+            .filter_map(|role| Some((role.id?, role.role_name)))
+            .collect();
+        let mut job_roles: HashMap<u64, HashSet<String>> = HashMap::new();
+        for bind in role_jobs {
+            if let Some(role_name) = role_names.get(&bind.role_id) {
+                job_roles
+                    .entry(bind.job_id)
+                    .or_default()
+                    .insert(role_name.clone());
+            }
+        }
+        Self {
+            role_names,
+            edges,
+            job_roles,
+        }
+    }
+
+    fn role_id(&self, role_name: &str) -> Option<u64> {
+        self.role_names
+            .iter()
+            .find(|(_, name)| name.as_str() == role_name)
+            .map(|(id, _)| *id)
+    }
+
+    // 包括通过 RoleParent 继承到的角色
+    fn effective_roles(&self, role_ids: impl IntoIterator<Item = u64>) -> HashSet<String> {
+        inherited_roles(role_ids, &self.edges)
+            .iter()
+            .filter_map(|role_id| self.role_names.get(role_id).cloned())
+            .collect()
+    }
+}
+
+struct Entry<T> {
+    value: Arc<T>,
+    loaded_at: Instant,
+}
+
+impl<T> Entry<T> {
+    fn new(value: Arc<T>) -> Self {
+        Self {
+            value,
+            loaded_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub users: usize,
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f64 {
+    match hits + misses {
+        0 => 0.0,
+        total => hits as f64 / total as f64,
+    }
+}
+
+pub struct PermissionCache {
+    ttl: Duration,
+    max_users: usize,
+    matrix: RwLock<Option<Entry<Matrix>>>,
+    // user_id -> 生效的角色
+    users: RwLock<HashMap<u64, Entry<HashSet<String>>>>,
+    // 每次失效加一，加载期间发生了失效就不写入缓存
+    version: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PermissionCache {
+    fn new(ttl: Duration, max_users: usize) -> Self {
+        Self {
+            ttl,
+            max_users,
+            matrix: RwLock::new(None),
+            users: RwLock::new(HashMap::new()),
+            version: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn cached_matrix(&self) -> Option<Arc<Matrix>> {
+        let matrix = self.matrix.read().unwrap();
+        let entry = matrix.as_ref()?;
+        (entry.loaded_at.elapsed() < self.ttl).then(|| entry.value.clone())
+    }
+
+    async fn matrix(&self) -> anyhow::Result<Arc<Matrix>> {
+        if let Some(matrix) = self.cached_matrix() {
+            return Ok(matrix);
+        }
+        let version = self.version.load(Ordering::SeqCst);
+        let matrix = Arc::new(Matrix::new(
+            Role::get_all().await?,
+            RoleParent::get_all().await?,
+            RoleBindJob::get_all().await?,
+        ));
+        let mut cached = self.matrix.write().unwrap();
+        if self.version.load(Ordering::SeqCst) == version {
+            *cached = Some(Entry::new(matrix.clone()));
+        }
+        Ok(matrix)
+    }
+
+    fn cached_user(&self, user_id: u64) -> Option<Arc<HashSet<String>>> {
+        let users = self.users.read().unwrap();
+        let entry = users.get(&user_id)?;
+        (entry.loaded_at.elapsed() < self.ttl).then(|| entry.value.clone())
+    }
+
+    pub async fn user_roles(&self, user_id: u64) -> anyhow::Result<Arc<HashSet<String>>> {
+        if let Some(roles) = self.cached_user(user_id) {
+            self.record(true);
+            return Ok(roles);
+        }
+        self.record(false);
+        let version = self.version.load(Ordering::SeqCst);
+        let role_ids = UserBindRole::get_role_ids(user_id).await?;
+        let matrix = self.matrix().await?;
+        // 登录的用户都隐含 normal，之前注册时没有写入绑定的用户也能访问 normal 的路由
+        let role_ids = role_ids.into_iter().chain(matrix.role_id(DEFAULT_ROLE));
+        let roles = Arc::new(matrix.effective_roles(role_ids));
+        let mut users = self.users.write().unwrap();
+        if self.version.load(Ordering::SeqCst) == version {
+            if users.len() >= self.max_users {
+                users.clear();
+            }
+            users.insert(user_id, Entry::new(roles.clone()));
+        }
+        Ok(roles)
+    }
+
+    pub async fn job_roles(&self, job_id: u64) -> anyhow::Result<HashSet<String>> {
+        let matrix = match self.cached_matrix() {
+            Some(matrix) => {
+                self.record(true);
+                matrix
+            }
+            None => {
+                self.record(false);
+                self.matrix().await?
+            }
+        };
+        Ok(matrix.job_roles.get(&job_id).cloned().unwrap_or_default())
+    }
+
+    pub fn invalidate(&self) {
+        self.version.fetch_add(1, Ordering::SeqCst);
+        *self.matrix.write().unwrap() = None;
+        self.users.write().unwrap().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        CacheStats {
+            hits,
+            misses,
+            hit_rate: hit_rate(hits, misses),
+            users: self.users.read().unwrap().len(),
+        }
+    }
+}
+
+// 修改角色、权限之后调用，其他实例通过 redis 收到通知
+pub async fn changed() {
+    PERMISSION_CACHE.invalidate();
+    if let Err(e) = publish_permission_changed().await {
+        warn!("publish permission changed err,{}", e);
+    }
+}
+
+async fn on_changed() {
+    PERMISSION_CACHE.invalidate();
+    if let Err(e) = validate_auth::init_router().await {
+        warn!("reload job router err,{}", e);
+    }
+}
+
+// 断开后重连，重连期间可能错过通知，所以每次订阅成功后都失效一次
+pub async fn subscribe() {
+    loop {
+        match subscribe_permission_changed().await {
+            Ok(mut pubsub) => {
+                on_changed().await;
+                let mut messages = pubsub.on_message();
+                while messages.next().await.is_some() {
+                    on_changed().await;
+                }
+                warn!("permission channel closed");
+            }
+            Err(e) => warn!("subscribe permission channel err,{}", e),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(id: u64, role_name: &str) -> Role {
+        Role {
+            id: Some(id),
+            role_name: role_name.to_string(),
+        }
+    }
+
+    fn edge(role_id: u64, parent_id: u64) -> RoleParent {
+        RoleParent {
+            id: None,
+            role_id,
+            parent_id,
+        }
+    }
+
+    #[test]
+    fn matrix() {
+        let matrix = Matrix::new(
+            vec![role(1, "root"), role(2, "worker"), role(3, "normal")],
+            vec![edge(2, 1), edge(3, 2)],
+            vec![
+                RoleBindJob {
+                    id: None,
+                    role_id: 2,
+                    job_id: 10,
+                },
+                RoleBindJob {
+                    id: None,
+                    role_id: 4,
+                    job_id: 10,
+                },
+            ],
+        );
+        assert_eq!(
+            matrix.effective_roles(vec![2]),
+            HashSet::from(["worker".to_string(), "normal".to_string()])
+        );
+        assert_eq!(matrix.effective_roles(vec![1]).len(), 3);
+        assert!(matrix.effective_roles(vec![]).is_empty());
+        assert_eq!(matrix.role_id(DEFAULT_ROLE), Some(3));
+        assert_eq!(matrix.role_id("user"), None);
+        // 已经删除的角色忽略
+        assert_eq!(matrix.job_roles[&10], HashSet::from(["worker".to_string()]));
+    }
+
+    #[test]
+    fn stats() {
+        assert_eq!(hit_rate(0, 0), 0.0);
+        assert_eq!(hit_rate(3, 1), 0.75);
+        let cache = PermissionCache::new(Duration::from_secs(60), 10);
+        cache.record(true);
+        cache.record(false);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                hit_rate: 0.5,
+                users: 0,
+            }
+        );
+        cache.users.write().unwrap().insert(
+            1,
+            Entry::new(Arc::new(HashSet::from(["normal".to_string()]))),
+        );
+        assert!(cache.cached_user(1).is_some());
+        cache.invalidate();
+        assert!(cache.cached_user(1).is_none());
+        assert_eq!(cache.version.load(Ordering::SeqCst), 1);
+    }
+}
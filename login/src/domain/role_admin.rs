@@ -0,0 +1,162 @@
+use anyhow::anyhow;
+use serde::Serialize;
+use tracing::info;
+
+use crate::domain::job_router::normalize_method;
+use crate::domain::permission_cache;
+use crate::domain::validate_auth::{set_job_auth, set_role_auth};
+use crate::repo::auth::{Job, Role, RoleBindJob, UserBindRole, DEFAULT_ROLE, ROOT_ROLE};
+use crate::repo::user::get_user_by_info;
+
+#[derive(Serialize, Debug)]
+pub struct UrlPermission {
+    pub method: String,
+    pub url: String,
+}
+
+fn check_role_name(role_name: &str) -> anyhow::Result<()> {
+    let valid = (1..=32).contains(&role_name.len())
+        && role_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(anyhow!("invalid role name {}", role_name));
+    }
+    Ok(())
+}
+
+async fn get_role(role_name: &str) -> anyhow::Result<Role> {
+    Role::get_by_name(role_name)
+        .await
+        .ok_or(anyhow!("role {} not found", role_name))
+}
+
+pub async fn create_role(operator: &str, role_name: &str) -> anyhow::Result<()> {
+    check_role_name(role_name)?;
+    if !Role::create(role_name).await? {
+        return Err(anyhow!("role {} already exists", role_name));
+    }
+    info!(target: "security", "{} create role {}", operator, role_name);
+    Ok(())
+}
+
+// root 和注册时默认的角色不能删除
+pub async fn delete_role(operator: &str, role_name: &str) -> anyhow::Result<()> {
+    if role_name == ROOT_ROLE || role_name == DEFAULT_ROLE {
+        return Err(anyhow!("role {} can't be deleted", role_name));
+    }
+    get_role(role_name).await?.delete().await?;
+    permission_cache::changed().await;
+    info!(target: "security", "{} delete role {}", operator, role_name);
+    Ok(())
+}
+
+pub async fn grant_user_role(
+    operator: &str,
+    user_name: &str,
+    role_name: &str,
+) -> anyhow::Result<()> {
+    get_role(role_name).await?;
+    set_role_auth(user_name, role_name).await?;
+    info!(target: "security", "{} grant role {} to {}", operator, role_name, user_name);
+    Ok(())
+}
+
+pub async fn revoke_user_role(
+    operator: &str,
+    user_name: &str,
+    role_name: &str,
+) -> anyhow::Result<()> {
+    let role = get_role(role_name).await?;
+    let user_id = get_user_by_info(user_name, None).await?.id.unwrap_or(0);
+    // 至少保留一个 root，否则没有人可以再管理角色
+    if role_name == ROOT_ROLE {
+        let users = role.get_users().await?;
+        if users.iter().all(|user| user.id == Some(user_id)) {
+            return Err(anyhow!("can't revoke the last {}", ROOT_ROLE));
+        }
+    }
+    if !UserBindRole::delete(user_id, role.id.unwrap_or(0)).await? {
+        return Err(anyhow!("{} doesn't have role {}", user_name, role_name));
+    }
+    permission_cache::changed().await;
+    info!(target: "security", "{} revoke role {} from {}", operator, role_name, user_name);
+    Ok(())
+}
+
+pub async fn grant_url(
+    operator: &str,
+    method: &str,
+    url: &str,
+    role_name: &str,
+) -> anyhow::Result<()> {
+    get_role(role_name).await?;
+    set_job_auth(method, url.to_string(), role_name).await?;
+    info!(target: "security", "{} grant {} {} to role {}", operator, method, url, role_name);
+    Ok(())
+}
+
+// job 保留在路由中，没有角色绑定时所有人都不能访问
+pub async fn revoke_url(
+    operator: &str,
+    method: &str,
+    url: &str,
+    role_name: &str,
+) -> anyhow::Result<()> {
+    let role = get_role(role_name).await?;
+    let job = Job::get_by_info(&normalize_method(method), url)
+        .await?
+        .ok_or(anyhow!("url {} {} not found", method, url))?;
+    if !RoleBindJob::delete(role.id.unwrap_or(0), job.id.unwrap_or(0)).await? {
+        return Err(anyhow!("{} {} isn't granted to {}", method, url, role_name));
+    }
+    permission_cache::changed().await;
+    info!(target: "security", "{} revoke {} {} from role {}", operator, method, url, role_name);
+    Ok(())
+}
+
+pub async fn list_roles() -> anyhow::Result<Vec<String>> {
+    Ok(Role::get_all()
+        .await?
+        .into_iter()
+        .map(|role| role.role_name)
+        .collect())
+}
+
+// 只列出直接绑定的 url，不包括继承的
+pub async fn list_permissions(role_name: &str) -> anyhow::Result<Vec<UrlPermission>> {
+    Ok(get_role(role_name)
+        .await?
+        .get_jobs()
+        .await?
+        .into_iter()
+        .map(|job| UrlPermission {
+            method: job.method,
+            url: job.url,
+        })
+        .collect())
+}
+
+pub async fn list_members(role_name: &str) -> anyhow::Result<Vec<String>> {
+    Ok(get_role(role_name)
+        .await?
+        .get_users()
+        .await?
+        .into_iter()
+        .map(|user| user.user_name)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_name() {
+        assert!(check_role_name("worker").is_ok());
+        assert!(check_role_name("shop-keeper_2").is_ok());
+        assert!(check_role_name("").is_err());
+        assert!(check_role_name("a b").is_err());
+        assert!(check_role_name(&"a".repeat(33)).is_err());
+    }
+}
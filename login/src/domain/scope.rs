@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use tracing::{info, warn};
+use util::pb::client::split_route;
+use util::scope::Scope;
+
+use crate::config::{ClientConfig, CONFIG};
+use crate::domain::permission_cache::PERMISSION_CACHE;
+use crate::domain::trans_to_token::issue_client_token;
+use crate::domain::validate_auth::LOGIN_URL_AUTHS;
+
+fn scopes_of(roles: &HashSet<String>, role_scopes: &HashMap<String, Vec<Scope>>) -> Vec<Scope> {
+    let mut scopes: Vec<Scope> = roles
+        .iter()
+        .filter_map(|role| role_scopes.get(role))
+        .flatten()
+        .cloned()
+        .collect();
+    scopes.sort_by_key(|scope| scope.to_string());
+    scopes.dedup();
+    scopes
+}
+
+pub async fn user_scopes(user_id: u64) -> anyhow::Result<Vec<Scope>> {
+    let roles = PERMISSION_CACHE.user_roles(user_id).await?;
+    Ok(scopes_of(&roles, &CONFIG.role_scopes))
+}
+
+// 耗时和第一个不同的字节的位置无关
+fn secret_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn find_client<'a>(
+    clients: &'a [ClientConfig],
+    client_id: &str,
+    client_secret: &str,
+) -> Option<&'a ClientConfig> {
+    clients
+        .iter()
+        .find(|client| client.client_id == client_id)
+        .filter(|client| secret_eq(&client.secret, client_secret))
+}
+
+// 返回 token 和有效期
+pub fn client_token(client_id: &str, client_secret: &str) -> anyhow::Result<(String, i64)> {
+    let client = match find_client(&CONFIG.clients, client_id, client_secret) {
+        Some(client) => client,
+        None => {
+            warn!(target: "security", "invalid client credential {}", client_id);
+            return Err(anyhow!("invalid client credential"));
+        }
+    };
+    let token = issue_client_token(client)?;
+    info!(target: "security", "issue client token to {}", client_id);
+    Ok((token, client.ttl_secs))
+}
+
+// pattern 以 /** 结尾时匹配它下面的所有路由
+fn covers(pattern: &str, url: &str) -> bool {
+    match pattern.strip_suffix("/**") {
+        Some(prefix) => url == prefix || url.starts_with(&format!("{}/", prefix)),
+        None => pattern == url,
+    }
+}
+
+fn is_login_route(url: &str) -> bool {
+    LOGIN_URL_AUTHS.iter().any(|(route, _)| {
+        let (_, owned) = split_route(route);
+        covers(owned, url) || covers(url, owned)
+    })
+}
+
+fn check_route(client: &ClientConfig, url: &str, role_name: &str) -> anyhow::Result<()> {
+    if is_login_route(url) {
+        return Err(anyhow!("{} is owned by login", url));
+    }
+    if !client.routes.iter().any(|route| route == url) {
+        return Err(anyhow!(
+            "client {} can't register {}",
+            client.client_id,
+            url
+        ));
+    }
+    if !client.roles.iter().any(|role| role == role_name) {
+        return Err(anyhow!(
+            "client {} can't bind routes to {}",
+            client.client_id,
+            role_name
+        ));
+    }
+    Ok(())
+}
+
+// 注册路由的 client 只能把配置中列出的路由绑定到配置中列出的角色，不能修改 login 自己的路由
+pub fn check_client_route(client_id: &str, url: &str, role_name: &str) -> anyhow::Result<()> {
+    let client = CONFIG
+        .clients
+        .iter()
+        .find(|client| client.client_id == client_id)
+        .ok_or(anyhow!("unknown client {}", client_id))?;
+    let result = check_route(client, url, role_name);
+    if let Err(e) = &result {
+        warn!(target: "security", "reject add_url_auth,{}", e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(scopes: &[&str]) -> Vec<Scope> {
+        scopes.iter().map(|scope| scope.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn role_scopes() {
+        let role_scopes = HashMap::from([
+            (
+                "worker".to_string(),
+                scopes(&["record:send", "record:sign"]),
+            ),
+            ("root".to_string(), scopes(&["wallet:admin", "record:send"])),
+        ]);
+        let roles = HashSet::from(["root".to_string(), "worker".to_string()]);
+        assert_eq!(
+            scopes_of(&roles, &role_scopes),
+            scopes(&["record:send", "record:sign", "wallet:admin"])
+        );
+        assert!(scopes_of(&HashSet::from(["normal".to_string()]), &role_scopes).is_empty());
+    }
+
+    #[test]
+    fn client() {
+        let clients = vec![ClientConfig {
+            client_id: "sale".to_string(),
+            secret: "secret".to_string(),
+            scopes: scopes(&["wallet:debit"]),
+            routes: vec![],
+            roles: vec![],
+            ttl_secs: 60,
+        }];
+        assert!(find_client(&clients, "sale", "secret").is_some());
+        assert!(find_client(&clients, "sale", "secre").is_none());
+        assert!(find_client(&clients, "sale", "secreT").is_none());
+        assert!(find_client(&clients, "comment", "secret").is_none());
+    }
+
+    #[test]
+    fn client_route() {
+        let client = ClientConfig {
+            client_id: "sale".to_string(),
+            secret: "secret".to_string(),
+            scopes: scopes(&["auth:register"]),
+            routes: vec!["/pay".to_string(), "/role/create".to_string()],
+            roles: vec!["normal".to_string(), "worker".to_string()],
+            ttl_secs: 60,
+        };
+        assert!(check_route(&client, "/pay", "normal").is_ok());
+        assert!(check_route(&client, "/pay", "root").is_err());
+        assert!(check_route(&client, "/send", "worker").is_err());
+        // 配置中列出也不能覆盖 login 的路由
+        assert!(check_route(&client, "/role/create", "normal").is_err());
+        assert!(is_login_route("/role/**"));
+        assert!(is_login_route("/Validate.RoleAdmin/grant_url"));
+        assert!(is_login_route("/**"));
+        assert!(is_login_route("/add_auth"));
+        assert!(!is_login_route("/add_item"));
+        assert!(!is_login_route("/roles"));
+    }
+}
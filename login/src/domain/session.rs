@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use anyhow::anyhow;
+use chrono::{Duration, Local};
+use serde::Serialize;
+
+use crate::cache::redis::{get_sessions, revoke_session, set_session};
+
+// 发起登录、刷新的客户端
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    pub user_agent: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Session {
+    pub session_id: String,
+    pub user_agent: String,
+    pub ip: String,
+    pub created_at: i64,
+    pub last_used_at: i64,
+    // 是否是当前 token 所在的 session
+    pub current: bool,
+}
+
+impl Session {
+    fn from_fields(session_id: String, mut fields: HashMap<String, String>, sid: &str) -> Self {
+        let mut take = |name: &str| fields.remove(name).unwrap_or_default();
+        Self {
+            current: session_id == sid,
+            session_id,
+            user_agent: take("user_agent"),
+            ip: take("ip"),
+            created_at: take("created_at").parse().unwrap_or(0),
+            last_used_at: take("last_used_at").parse().unwrap_or(0),
+        }
+    }
+}
+
+pub async fn create(family: &str, client: &ClientInfo, timeout: Duration) -> anyhow::Result<()> {
+    let now = Local::now().timestamp().to_string();
+    let fields = [
+        ("user_agent", client.user_agent.clone()),
+        ("ip", client.ip.to_string()),
+        ("created_at", now.clone()),
+        ("last_used_at", now),
+    ];
+    set_session(family, &fields, timeout).await?;
+    Ok(())
+}
+
+// 刷新 token 时更新最后使用的时间和 ip
+pub async fn touch(family: &str, client: &ClientInfo, timeout: Duration) -> anyhow::Result<()> {
+    let fields = [
+        ("ip", client.ip.to_string()),
+        ("last_used_at", Local::now().timestamp().to_string()),
+    ];
+    set_session(family, &fields, timeout).await?;
+    Ok(())
+}
+
+pub async fn list(user_id: u64, sid: &str) -> anyhow::Result<Vec<Session>> {
+    let mut sessions: Vec<Session> = get_sessions(user_id)
+        .await?
+        .into_iter()
+        .map(|(session_id, fields)| Session::from_fields(session_id, fields, sid))
+        .collect();
+    sessions.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at));
+    Ok(sessions)
+}
+
+pub async fn revoke(user_id: u64, session_id: &str) -> anyhow::Result<()> {
+    // 记录的时间覆盖 access token 的有效期
+    if !revoke_session(user_id, session_id, Duration::days(1)).await? {
+        return Err(anyhow!("session not found"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_fields() {
+        let fields = HashMap::from([
+            ("user_agent".to_string(), "curl/7.88".to_string()),
+            ("ip".to_string(), "127.0.0.1".to_string()),
+            ("created_at".to_string(), "100".to_string()),
+            ("last_used_at".to_string(), "200".to_string()),
+        ]);
+        let session = Session::from_fields("f".to_string(), fields, "f");
+        assert_eq!(
+            session,
+            Session {
+                session_id: "f".to_string(),
+                user_agent: "curl/7.88".to_string(),
+                ip: "127.0.0.1".to_string(),
+                created_at: 100,
+                last_used_at: 200,
+                current: true,
+            }
+        );
+        let session = Session::from_fields("f".to_string(), HashMap::new(), "g");
+        assert!(!session.current);
+        assert_eq!(session.created_at, 0);
+    }
+}
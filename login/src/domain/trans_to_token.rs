@@ -1,79 +1,188 @@
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::Add;
-use std::string::FromUtf8Error;
 
-use base64::engine::general_purpose::STANDARD as base64;
-use base64::{DecodeError, Engine};
+use anyhow::anyhow;
 use chrono::prelude::*;
 use chrono::Duration;
-use hex::FromHexError;
-use hmac::digest::MacError;
-use hmac::{Hmac, Mac};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
 use lazy_static::lazy_static;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 use thiserror::Error;
+use tracing::warn;
+use util::scope::Scope;
 
 use crate::api::login::{LoginRequest, SignRequest};
-use crate::cache::redis::{get_user_by_rt, set_rt_with_ttl};
+use crate::cache::redis::{
+    consume_rt, get_user_by_family, is_revoked, revoke_family, revoke_jti, revoke_user,
+    set_rt_with_ttl, CacheErr, ConsumedRT,
+};
+use crate::config::{ClientConfig, CONFIG};
+use crate::domain::credential_policy::POLICY;
+use crate::domain::key_ring::KeyRing;
+use crate::domain::login_limit;
+use crate::domain::otp::{self, OtpChallenge};
+use crate::domain::scope;
+use crate::domain::session::{self, ClientInfo};
+use crate::repo::auth::{InsertIntoRole, DEFAULT_ROLE};
 use crate::repo::user::{create_user, get_user_by_info, User};
 
 lazy_static! {
-    static ref HMAC_SHA256: Hmac<Sha256> =
-        Hmac::<Sha256>::new_from_slice(b"magic secret key").unwrap();
+    pub static ref KEY_RING: KeyRing =
+        KeyRing::from_config(&CONFIG.token).expect("load key ring err");
 }
 
 type TokenRT = (String, String);
 
-pub async fn trans(request: LoginRequest) -> anyhow::Result<TokenRT> {
-    let expire_time = Duration::days(1);
+pub enum LoginResult {
+    Token(TokenRT),
+    // 需要 otp，通过 verify_otp 完成登录
+    Challenge(OtpChallenge),
+}
+
+pub async fn trans(request: LoginRequest, client: &ClientInfo) -> anyhow::Result<LoginResult> {
+    let ip = client.ip;
+    login_limit::reserve(&request.user_name, ip).await?;
+    // 密码错误时失败已经由 reserve 记录
     let user = get_user_by_info(&request.user_name, Some(&request.pass_word)).await?;
-    let user_info = serde_json::to_string(&UserToken::new(&user, expire_time)).unwrap();
-    Ok(get_set_token(user_info, expire_time * 7).await)
+    login_limit::record_success(&request.user_name, ip).await?;
+    if let Some(challenge) = otp::login_challenge(&user).await? {
+        return Ok(LoginResult::Challenge(challenge));
+    }
+    Ok(LoginResult::Token(issue_token(&user, client).await?))
 }
 
-pub async fn refresh_token(rt: String) -> anyhow::Result<TokenRT> {
+pub async fn verify_otp(
+    challenge: &str,
+    code: &str,
+    client: &ClientInfo,
+) -> anyhow::Result<TokenRT> {
+    let user = otp::verify_challenge(challenge, code, client.ip).await?;
+    issue_token(&user, client).await
+}
+
+pub async fn refresh_token(rt: String, client: &ClientInfo) -> anyhow::Result<TokenRT> {
     let expire_time = Duration::days(1);
-    let user_str = get_user_by_rt(&rt).await?;
-    let user: UserToken = serde_json::from_str(&user_str)?;
-    let user_str = serde_json::to_string(&user.refresh(expire_time)).unwrap();
-    Ok(get_set_token(user_str, expire_time * 7).await)
+    let family = match consume_rt(&rt, expire_time * 7).await? {
+        ConsumedRT::Valid(family) => family,
+        ConsumedRT::Reused(family) => {
+            // 已经被使用过的 rt 再次出现，说明 rt 可能被盗，整个 family 作废
+            warn!(
+                target: "security",
+                "refresh token reused, revoke family {}", family
+            );
+            revoke_family(&family, expire_time).await?;
+            return Err(anyhow!("refresh token reused"));
+        }
+        ConsumedRT::NotFound => return Err(anyhow!("invalid refresh token")),
+    };
+    let user_str = get_user_by_family(&family)
+        .await?
+        .ok_or(anyhow!("refresh token revoked"))?;
+    let mut user: UserToken = serde_json::from_str(&user_str)?;
+    // 角色可能已经变化
+    user.scopes = scope::user_scopes(user.user_id).await?;
+    let token_rt = get_set_token(user.refresh(expire_time), &family, expire_time * 7).await?;
+    session::touch(&family, client, expire_time * 7).await?;
+    Ok(token_rt)
+}
+
+pub async fn sign_by_req(request: SignRequest, client: &ClientInfo) -> anyhow::Result<TokenRT> {
+    POLICY.check_sign(&request.user_name, &request.pass_word)?;
+    let mut user = create_user(request.user_name, request.pass_word).await?;
+    user.insert_into_role(DEFAULT_ROLE).await?;
+    issue_token(&user, client).await
 }
 
-pub async fn sign_by_req(request: SignRequest) -> anyhow::Result<TokenRT> {
+// 新的登录，生成新的 family
+async fn issue_token(user: &User, client: &ClientInfo) -> anyhow::Result<TokenRT> {
     let expire_time = Duration::days(1);
-    let user = create_user(request.user_name, request.pass_word).await?;
-    let user_info = serde_json::to_string(&UserToken::new(&user, expire_time)).unwrap();
-    Ok(get_set_token(user_info, expire_time * 7).await)
+    let family = new_family();
+    let scopes = scope::user_scopes(user.id.unwrap_or(0)).await?;
+    let user_token = UserToken::new(user, &KEY_RING.issuer, &family, expire_time, scopes);
+    let token_rt = get_set_token(user_token, &family, expire_time * 7).await?;
+    session::create(&family, client, expire_time * 7).await?;
+    Ok(token_rt)
 }
 
-async fn get_set_token(user_info: String, expire: Duration) -> TokenRT {
-    let (token, rt) = user_to_token(&user_info);
-    if let Err(e) = set_rt_with_ttl(&rt, &user_info, expire).await {
-        fast_log::print(format!("set rt err {}", e)).unwrap_or(())
-    };
-    (token, rt)
+fn new_family() -> String {
+    uuid::Uuid::new_v4().to_string()
 }
 
-fn user_to_token(user_info: &str) -> TokenRT {
-    let mut hmac_sha256 = HMAC_SHA256.clone();
-    hmac_sha256.update(user_info.as_ref());
-    let hmac_result = hex::encode(hmac_sha256.finalize().into_bytes().to_vec());
-    let token = base64.encode(format!("{}.{}", user_info, hmac_result));
-    let rt = hex::encode(md5::compute(&token).to_vec());
-    (token, rt)
+async fn get_set_token(
+    user_token: UserToken,
+    family: &str,
+    expire: Duration,
+) -> anyhow::Result<TokenRT> {
+    let user_info = serde_json::to_string(&user_token)?;
+    let token = encode_token(&KEY_RING, &user_token)?;
+    let rt = new_rt();
+    set_rt_with_ttl(&rt, family, user_token.user_id, &user_info, expire).await?;
+    Ok((token, rt))
+}
+
+// 和 access token 无关，拿到 access token 的服务和日志都不能推算出 rt
+fn new_rt() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// client token 不对应 refresh token，过期后重新获取
+pub fn issue_client_token(client: &ClientConfig) -> anyhow::Result<String> {
+    let user_token = UserToken::for_client(client, &KEY_RING.issuer);
+    Ok(encode_token(&KEY_RING, &user_token)?)
+}
+
+fn encode_token(ring: &KeyRing, user: &UserToken) -> jsonwebtoken::errors::Result<String> {
+    let (kid, algorithm, key) = ring.signing_key();
+    let mut header = Header::new(algorithm);
+    header.kid = Some(kid.to_string());
+    encode(&header, user, key)
+}
+
+fn decode_token(ring: &KeyRing, token: &str) -> Result<UserToken, ValidateErr> {
+    let kid = decode_header(token)?.kid.ok_or(ValidateErr::UnknownKid)?;
+    let key = ring.verifying_key(&kid).ok_or(ValidateErr::UnknownKid)?;
+    let mut validation = Validation::new(key.algorithm);
+    validation.set_issuer(&[&ring.issuer]);
+    validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+    match decode::<UserToken>(token, &key.decoding, &validation) {
+        Ok(data) => Ok(data.claims),
+        Err(e) if matches!(e.kind(), ErrorKind::ExpiredSignature) => Err(ValidateErr::Expire),
+        Err(e) => Err(ValidateErr::JWTErr(e)),
+    }
+}
+
+pub async fn logout(user: &UserToken) -> anyhow::Result<()> {
+    revoke_jti(
+        &user.jti,
+        Duration::seconds(user.time_out - Local::now().timestamp()),
+    )
+    .await?;
+    if !user.sid.is_empty() {
+        session::revoke(user.user_id, &user.sid).await?;
+    }
+    Ok(())
+}
+
+// 修改密码等场景也通过这里作废用户所有的 token
+pub async fn logout_all(user_id: u64) -> anyhow::Result<()> {
+    let expire_time = Duration::days(1);
+    revoke_user(user_id, Local::now().timestamp(), expire_time).await?;
+    Ok(())
 }
 
 #[derive(Debug, Error)]
 pub enum ValidateErr {
-    DecodeBase64Err(#[from] DecodeError),
-    TransUTF8Err(#[from] FromUtf8Error),
-    LenErr,
-    ValidateHmacErr(#[from] MacError),
-    ValidateHexErr(#[from] FromHexError),
-    JSONErr(#[from] serde_json::Error),
+    JWTErr(#[from] jsonwebtoken::errors::Error),
+    UnknownKid,
     Expire,
+    Revoked,
+    CacheErr(#[from] CacheErr),
 }
 
 impl Display for ValidateErr {
@@ -82,71 +191,180 @@ impl Display for ValidateErr {
     }
 }
 
-pub fn validate(token: &str) -> Result<UserToken, ValidateErr> {
-    let decode_token = String::from_utf8(base64.decode(token)?)?;
-    let str_hmac: Vec<&str> = decode_token.split(".").collect();
-    if str_hmac.len() != 2 {
-        return Err(ValidateErr::LenErr);
-    }
-    let str_user = str_hmac[0];
-    let hmac = str_hmac[1];
-
-    let mut hmac_sha256 = HMAC_SHA256.clone();
-    hmac_sha256.update(str_user.as_ref());
-    hmac_sha256.verify_slice(hex::decode(hmac.as_bytes())?.as_ref())?;
-    let user: UserToken = serde_json::from_str(str_user)?;
-    if !user.valid() {
-        return Err(ValidateErr::Expire);
+pub async fn validate(token: &str) -> Result<UserToken, ValidateErr> {
+    let user = decode_token(&KEY_RING, token)?;
+    if is_revoked(&user.jti, &user.sid, user.user_id, user.iat).await? {
+        return Err(ValidateErr::Revoked);
     }
     Ok(user)
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct UserToken {
+    pub iss: String,
+    pub sub: String,
+    pub iat: i64,
+    #[serde(rename = "exp")]
+    pub time_out: i64,
+    pub jti: String,
+    // 对应 refresh token 的 family
+    #[serde(default)]
+    pub sid: String,
     pub user_name: String,
     pub user_id: u64,
+    #[serde(default)]
     pub data: HashMap<String, String>,
-    pub time_out: i64,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
 }
 
 impl UserToken {
-    fn new(user_info: &User, expire_time: Duration) -> Self {
+    fn new(
+        user_info: &User,
+        issuer: &str,
+        sid: &str,
+        expire_time: Duration,
+        scopes: Vec<Scope>,
+    ) -> Self {
+        let user_id = user_info.id.unwrap_or(0);
+        let now = Local::now();
         Self {
+            iss: issuer.to_string(),
+            sub: user_id.to_string(),
+            iat: now.timestamp(),
+            time_out: now.add(expire_time).timestamp(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            sid: sid.to_string(),
             user_name: user_info.user_name.clone(),
-            user_id: user_info.id.unwrap_or(0),
+            user_id,
             data: Default::default(),
-            time_out: Local::now().add(expire_time).timestamp(),
+            scopes,
         }
     }
-    fn valid(&self) -> bool {
-        return self.time_out > Local::now().timestamp();
+    // user_id 为 0，sub 为 client:<client_id>
+    fn for_client(client: &ClientConfig, issuer: &str) -> Self {
+        let now = Local::now();
+        Self {
+            iss: issuer.to_string(),
+            sub: format!("client:{}", client.client_id),
+            iat: now.timestamp(),
+            time_out: now.timestamp() + client.ttl_secs,
+            jti: uuid::Uuid::new_v4().to_string(),
+            sid: String::new(),
+            user_name: client.client_id.clone(),
+            user_id: 0,
+            data: Default::default(),
+            scopes: client.scopes.clone(),
+        }
     }
     fn refresh(mut self, expire_time: Duration) -> Self {
-        self.time_out = Local::now().add(expire_time).timestamp();
+        let now = Local::now();
+        self.iat = now.timestamp();
+        self.time_out = now.add(expire_time).timestamp();
+        self.jti = uuid::Uuid::new_v4().to_string();
         self
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use jsonwebtoken::Algorithm;
+
+    use crate::config::{KeyConfig, TokenConfig};
+
     use super::*;
 
-    #[test]
-    fn test_token() {
-        let user_token = UserToken::new(
+    fn test_ring(kid: &str, secret: &str) -> KeyRing {
+        KeyRing::from_config(&TokenConfig {
+            current_kid: kid.to_string(),
+            keys: vec![KeyConfig {
+                kid: kid.to_string(),
+                algorithm: Algorithm::HS256,
+                secret: Some(secret.to_string()),
+                private_key: None,
+                public_key: None,
+                retire_at: None,
+            }],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn test_user(expire_time: Duration) -> UserToken {
+        UserToken::new(
             &User {
                 id: Some(3),
                 user_name: "worker".to_string(),
                 pass_word: "worker".to_string(),
             },
-            Duration::weeks(7),
-        );
-        let user_info = serde_json::to_string(&user_token).unwrap();
-        let (token, _) = user_to_token(&user_info);
+            &TokenConfig::default().issuer,
+            "family",
+            expire_time,
+            vec!["record:send".parse().unwrap()],
+        )
+    }
+
+    #[test]
+    fn test_token() {
+        let ring = test_ring("test", "test secret");
+        let user_token = test_user(Duration::weeks(7));
+        let token = encode_token(&ring, &user_token).unwrap();
         dbg!(&token);
-        let check_user = validate(&token).unwrap();
+        let check_user = decode_token(&ring, &token).unwrap();
         assert_eq!(user_token, check_user);
     }
+
     #[test]
-    fn test_validate() {}
+    fn test_validate() {
+        let ring = test_ring("test", "test secret");
+        let token = encode_token(&ring, &test_user(Duration::days(-1))).unwrap();
+        assert!(matches!(
+            decode_token(&ring, &token),
+            Err(ValidateErr::Expire)
+        ));
+
+        let other = test_ring("test", "other secret");
+        let token = encode_token(&other, &test_user(Duration::days(1))).unwrap();
+        assert!(matches!(
+            decode_token(&ring, &token),
+            Err(ValidateErr::JWTErr(_))
+        ));
+
+        let other = test_ring("other", "test secret");
+        let token = encode_token(&other, &test_user(Duration::days(1))).unwrap();
+        assert!(matches!(
+            decode_token(&ring, &token),
+            Err(ValidateErr::UnknownKid)
+        ));
+    }
+
+    #[test]
+    fn test_rt() {
+        let rt = new_rt();
+        assert_eq!(rt.len(), 64);
+        assert_ne!(rt, new_rt());
+    }
+
+    #[test]
+    fn test_reuse() {
+        tokio_test::block_on(async {
+            let family = new_family();
+            let mut user = test_user(Duration::days(1));
+            user.sid = family.clone();
+            let (token, rt) = get_set_token(user, &family, Duration::days(7))
+                .await
+                .unwrap();
+            assert!(validate(&token).await.is_ok());
+
+            // 正常刷新之后同一个 rt 再次出现
+            consume_rt(&rt, Duration::days(7)).await.unwrap();
+            let client = ClientInfo {
+                ip: "127.0.0.1".parse().unwrap(),
+                user_agent: "test".to_string(),
+            };
+            assert!(refresh_token(rt, &client).await.is_err());
+            // 已经签发的 access token 也不能再使用
+            assert!(matches!(validate(&token).await, Err(ValidateErr::Revoked)));
+        })
+    }
 }
@@ -1,24 +1,80 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use tracing::warn;
+
+use crate::domain::job_router::{check_pattern, normalize_method, JobRouter};
+use crate::domain::permission_cache::{self, PERMISSION_CACHE};
 use crate::repo::{
-    auth::{InsertIntoRole, Job, Role},
+    auth::{InsertIntoRole, Job},
     user,
 };
 
-pub async fn validate(user_id: u64, url: &str) -> bool {
-    let user_role = Role::get_by_user(user_id).await;
-    let job_role = Role::get_by_job(url).await;
-    if user_role.contains("root") || job_role.contains("normal") {
-        return true;
+// login 自己的路由，其他服务不能通过 add_url_auth 修改
+pub const LOGIN_URL_AUTHS: &[(&str, &str)] = &[
+    ("POST /add_auth", "root"),
+    ("POST /delete_account", "root"),
+    ("POST /unlock_user", "root"),
+    ("POST /otp/admin_enroll", "root"),
+    ("/admin/log_filter", "root"),
+    ("/role/**", "root"),
+    ("POST /Validate.RoleAdmin/**", "root"),
+    ("POST /logout", "normal"),
+    ("POST /logout_all", "normal"),
+    ("POST /change_password", "normal"),
+    ("POST /otp/enroll", "normal"),
+    ("POST /otp/confirm", "normal"),
+    ("GET /sessions", "normal"),
+    ("POST /sessions/revoke", "normal"),
+];
+
+lazy_static! {
+    static ref JOB_ROUTER: RwLock<JobRouter> = RwLock::new(JobRouter::default());
+}
+
+// 启动和收到权限修改通知时，从数据库重新加载 job 路由
+pub async fn init_router() -> anyhow::Result<()> {
+    let mut router = JobRouter::default();
+    for job in Job::get_all().await? {
+        if let Err(e) = router.insert(&job.method, &job.url, job.id.unwrap_or(0)) {
+            warn!("skip job {:?},{}", job, e);
+        }
     }
-    user_role.into_iter().any(|role| job_role.contains(&role))
+    *JOB_ROUTER.write().unwrap() = router;
+    Ok(())
 }
 
-pub async fn set_job_auth(url: String, role_name: &str) -> anyhow::Result<()> {
-    Job::new(url.to_string()).insert_into_role(role_name).await
+// 用户拥有或者继承到 job 绑定的任意一个角色
+pub async fn validate(user_id: u64, method: &str, path: &str) -> bool {
+    let job_id = match JOB_ROUTER.read().unwrap().find(method, path) {
+        Some(job_id) => job_id,
+        None => return false,
+    };
+    let user_role = PERMISSION_CACHE
+        .user_roles(user_id)
+        .await
+        .unwrap_or_default();
+    let job_role = PERMISSION_CACHE.job_roles(job_id).await.unwrap_or_default();
+    user_role.iter().any(|role| job_role.contains(role))
+}
+
+pub async fn set_job_auth(method: &str, url: String, role_name: &str) -> anyhow::Result<()> {
+    check_pattern(&url)?;
+    let mut job = Job::new(normalize_method(method), url);
+    job.insert_into_role(role_name).await?;
+    JOB_ROUTER
+        .write()
+        .unwrap()
+        .insert(&job.method, &job.url, job.id.unwrap_or(0))?;
+    permission_cache::changed().await;
+    Ok(())
 }
 
 pub async fn set_role_auth(user_name: &str, role_name: &str) -> anyhow::Result<()> {
     user::get_user_by_info(user_name, None)
         .await?
         .insert_into_role(role_name)
-        .await
+        .await?;
+    permission_cache::changed().await;
+    Ok(())
 }
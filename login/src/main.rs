@@ -1,8 +1,16 @@
-use axum::{middleware, routing::post, Router};
+use std::net::SocketAddr;
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
 use tower_http::trace::TraceLayer;
+use util::health::Readiness;
 
 mod api;
 mod cache;
+mod config;
 mod domain;
 mod pb;
 mod repo;
@@ -13,22 +21,75 @@ async fn main() {
     //     std::env::set_var("RUST_LOG", "tower_http=debug,middleware=debug");
     // }
 
-    util::log_init::init::init();
+    let config = &*config::CONFIG;
+    util::log_init::init::init(&config.log);
+    util::trace::init("login", &config.trace);
 
     repo::init().await;
+    util::pb::tls::init(config.tls.clone());
+    lazy_static::initialize(&domain::trans_to_token::KEY_RING);
+    lazy_static::initialize(&domain::notify::NOTIFIER);
+    domain::validate_auth::init_router()
+        .await
+        .unwrap_or_else(|e| panic!("init job router failed,{}", e));
+    tokio::spawn(domain::permission_cache::subscribe());
+    init_url_auth(domain::validate_auth::LOGIN_URL_AUTHS).await;
 
-    tokio::spawn(async move { pb::server::grpc_server("0.0.0.0:8089").await });
+    tokio::spawn(async move { pb::server::grpc_server(&config.grpc_addr).await });
 
     let app = Router::new()
         .route("/add_auth", post(api::auth::add_auth))
+        .route("/logout", post(api::login::logout))
+        .route("/logout_all", post(api::login::logout_all))
+        .route("/change_password", post(api::account::change_password))
+        .route("/delete_account", post(api::account::delete_account))
+        .route("/unlock_user", post(api::auth::unlock_user))
+        .route("/otp/enroll", post(api::otp::enroll))
+        .route("/otp/confirm", post(api::otp::confirm))
+        .route("/otp/admin_enroll", post(api::otp::admin_enroll))
+        .route("/sessions", get(api::session::list))
+        .route("/sessions/revoke", post(api::session::revoke))
+        .route("/role/create", post(api::role::create))
+        .route("/role/delete", post(api::role::delete))
+        .route("/role/grant_user", post(api::role::grant_user))
+        .route("/role/revoke_user", post(api::role::revoke_user))
+        .route("/role/grant_url", post(api::role::grant_url))
+        .route("/role/revoke_url", post(api::role::revoke_url))
+        .route("/role/list", get(api::role::list))
+        .route("/role/permissions", get(api::role::permissions))
+        .route("/role/members", get(api::role::members))
+        .route("/role/cache_stats", get(api::role::cache_stats))
+        .merge(util::log_init::init::router())
         .layer(middleware::from_fn(api::validate::auth))
         .route("/login", post(api::login::login))
+        .route("/login/verify_otp", post(api::login::verify_otp))
         .route("/sign", post(api::login::sign))
         .route("/refresh_token", post(api::login::refresh))
-        .layer(TraceLayer::new_for_http());
+        .route("/request_reset", post(api::account::request_reset))
+        .route("/reset_password", post(api::account::reset_password))
+        .route("/.well-known/jwks.json", get(api::key::jwks))
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(util::axum::metrics::track))
+        .layer(util::trace::TraceContextLayer)
+        .merge(util::metrics::router())
+        .merge(util::health::router(
+            Readiness::new()
+                .check("database", repo::ping)
+                .check("redis", || async { Ok(cache::redis::ping().await?) }),
+        ));
 
-    axum::Server::bind(&"0.0.0.0:8080".parse().unwrap())
-        .serve(app.into_make_service())
+    axum::Server::bind(&config.http_addr.parse().unwrap())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
+
+// login 自己的路由不经过 grpc，直接写入 job
+async fn init_url_auth(url_auths: &[(&str, &str)]) {
+    for (route, auth) in url_auths {
+        let (method, url) = util::pb::client::split_route(route);
+        domain::validate_auth::set_job_auth(method, url.to_string(), auth)
+            .await
+            .unwrap_or_else(|e| panic!("init url failed,{},{},{}", route, auth, e))
+    }
+}
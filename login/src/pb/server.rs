@@ -1,16 +1,48 @@
-use tonic::{transport::Server, Request, Response, Status};
+use tonic::{Request, Response, Status};
 use tower_http::trace::TraceLayer;
 
+use crate::cache::redis::is_revoked;
+use crate::domain::permission_cache::PERMISSION_CACHE;
+use crate::domain::role_admin;
+use crate::domain::scope::{check_client_route, client_token};
+use crate::domain::trans_to_token::UserToken;
 use crate::domain::validate_auth::set_job_auth;
 use crate::domain::{
     trans_to_token::validate as token_validate, validate_auth::validate as auth_validate,
 };
 
-use crate::repo::auth::Role;
+use util::health::HealthReporter;
+use util::pb::metrics::GrpcMetricsLayer;
+use util::pb::require_scope;
+use util::pb::service_auth::{ServiceAuthLayer, TokenVerifier};
+use util::pb::tls;
 use util::pb::validate::{
+    role_admin_server::{RoleAdmin, RoleAdminServer},
     validate_server::{Validate, ValidateServer},
-    *,
+    UrlPermission as PbUrlPermission, *,
 };
+use util::scope::{AuthRegister, ScopeName};
+use util::trace::TraceContextLayer;
+
+fn user_info(user: UserToken) -> UserInfo {
+    UserInfo {
+        user_name: user.user_name,
+        user_id: user.user_id,
+        data: user.data,
+        time_out: user.time_out,
+        scopes: user.scopes.iter().map(|scope| scope.to_string()).collect(),
+    }
+}
+
+// login 自己校验 service token，不经过 grpc
+struct LocalVerifier;
+
+#[tonic::async_trait]
+impl TokenVerifier for LocalVerifier {
+    async fn verify(&self, token: &str) -> anyhow::Result<util::axum::auth::UserToken> {
+        Ok(user_info(token_validate(token).await?).into())
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct ValidateImpl {}
@@ -22,14 +54,9 @@ impl Validate for ValidateImpl {
         request: Request<TokenRequest>,
     ) -> Result<Response<UserInfo>, Status> {
         let user = token_validate(&request.into_inner().token)
+            .await
             .map_err(|e| Status::unknown(e.to_string()))?;
-        let info = UserInfo {
-            user_name: user.user_name,
-            user_id: user.user_id,
-            data: user.data,
-            time_out: user.time_out,
-        };
-        Ok(Response::new(info))
+        Ok(Response::new(user_info(user)))
     }
 
     async fn validate_auth(
@@ -38,7 +65,12 @@ impl Validate for ValidateImpl {
     ) -> Result<Response<AuthResponse>, Status> {
         let auth_request = request.into_inner();
         Ok(Response::new(AuthResponse {
-            ok: auth_validate(auth_request.user_id, &auth_request.url).await,
+            ok: auth_validate(
+                auth_request.user_id,
+                &auth_request.method,
+                &auth_request.url,
+            )
+            .await,
         }))
     }
 
@@ -57,6 +89,7 @@ impl Validate for ValidateImpl {
             .validate_auth(Request::new(AuthRequest {
                 user_id: user.user_id,
                 url: validate_request.url,
+                method: validate_request.method,
             }))
             .await?
             .into_inner();
@@ -70,8 +103,12 @@ impl Validate for ValidateImpl {
         &self,
         request: Request<AddUrlAuthRequest>,
     ) -> Result<Response<AddUrlAuthResponse>, Status> {
+        // 只有注册路由的 client 有这个 scope，并且只能注册配置中属于自己的路由
+        let client = require_scope(&request, AuthRegister::SCOPE)?;
         let auth_request = request.into_inner();
-        set_job_auth(auth_request.url, &auth_request.auth)
+        check_client_route(&client.user_name, &auth_request.url, &auth_request.auth)
+            .map_err(|e| Status::permission_denied(e.to_string()))?;
+        set_job_auth(&auth_request.method, auth_request.url, &auth_request.auth)
             .await
             .map_err(|e| Status::unknown(e.to_string()))?;
         Ok(Response::new(AddUrlAuthResponse { ok: true }))
@@ -81,18 +118,202 @@ impl Validate for ValidateImpl {
         &self,
         request: Request<GetAllAuthRequest>,
     ) -> Result<Response<GetAllAuthResponse>, Status> {
-        let roles = Role::get_by_user(request.into_inner().user_id).await;
+        let roles = PERMISSION_CACHE
+            .user_roles(request.into_inner().user_id)
+            .await
+            .map_err(|e| Status::unknown(e.to_string()))?;
         Ok(Response::new(GetAllAuthResponse {
-            auth_infos: Vec::from_iter(roles),
+            auth_infos: Vec::from_iter(roles.iter().cloned()),
         }))
     }
+
+    async fn check_revoked(
+        &self,
+        request: Request<RevokedRequest>,
+    ) -> Result<Response<RevokedResponse>, Status> {
+        let request = request.into_inner();
+        let revoked = is_revoked(
+            &request.jti,
+            &request.sid,
+            request.user_id,
+            request.issued_at,
+        )
+        .await
+        .map_err(|e| Status::unknown(e.to_string()))?;
+        Ok(Response::new(RevokedResponse { revoked }))
+    }
+
+    async fn client_token(
+        &self,
+        request: Request<ClientTokenRequest>,
+    ) -> Result<Response<ClientTokenResponse>, Status> {
+        let request = request.into_inner();
+        let (token, expires_in) = client_token(&request.client_id, &request.client_secret)
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+        Ok(Response::new(ClientTokenResponse { token, expires_in }))
+    }
+}
+
+// 和 http 一样按 job 鉴权，grpc 的请求都是 POST 到 /package.service/method
+#[allow(clippy::result_large_err)]
+async fn authorize<T>(request: &Request<T>, path: &str) -> Result<UserToken, Status> {
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|token| token.to_str().ok())
+        .ok_or(Status::unauthenticated("missing token"))?;
+    let user = token_validate(token)
+        .await
+        .map_err(|e| Status::unauthenticated(e.to_string()))?;
+    if !auth_validate(user.user_id, "POST", path).await {
+        return Err(Status::permission_denied("permission denied"));
+    }
+    Ok(user)
+}
+
+#[allow(clippy::result_large_err)]
+fn admin_resp(result: anyhow::Result<()>) -> Result<Response<RoleAdminResponse>, Status> {
+    result.map_err(|e| Status::failed_precondition(e.to_string()))?;
+    Ok(Response::new(RoleAdminResponse { ok: true }))
+}
+
+#[derive(Debug, Default)]
+pub struct RoleAdminImpl {}
+
+#[tonic::async_trait]
+impl RoleAdmin for RoleAdminImpl {
+    async fn create_role(
+        &self,
+        request: Request<RoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/create_role").await?;
+        let request = request.into_inner();
+        admin_resp(role_admin::create_role(&user.user_name, &request.role_name).await)
+    }
+
+    async fn delete_role(
+        &self,
+        request: Request<RoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/delete_role").await?;
+        let request = request.into_inner();
+        admin_resp(role_admin::delete_role(&user.user_name, &request.role_name).await)
+    }
+
+    async fn grant_user_role(
+        &self,
+        request: Request<UserRoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/grant_user_role").await?;
+        let request = request.into_inner();
+        admin_resp(
+            role_admin::grant_user_role(&user.user_name, &request.user_name, &request.role_name)
+                .await,
+        )
+    }
+
+    async fn revoke_user_role(
+        &self,
+        request: Request<UserRoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/revoke_user_role").await?;
+        let request = request.into_inner();
+        admin_resp(
+            role_admin::revoke_user_role(&user.user_name, &request.user_name, &request.role_name)
+                .await,
+        )
+    }
+
+    async fn grant_url(
+        &self,
+        request: Request<UrlRoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/grant_url").await?;
+        let request = request.into_inner();
+        admin_resp(
+            role_admin::grant_url(
+                &user.user_name,
+                &request.method,
+                &request.url,
+                &request.role_name,
+            )
+            .await,
+        )
+    }
+
+    async fn revoke_url(
+        &self,
+        request: Request<UrlRoleRequest>,
+    ) -> Result<Response<RoleAdminResponse>, Status> {
+        let user = authorize(&request, "/Validate.RoleAdmin/revoke_url").await?;
+        let request = request.into_inner();
+        admin_resp(
+            role_admin::revoke_url(
+                &user.user_name,
+                &request.method,
+                &request.url,
+                &request.role_name,
+            )
+            .await,
+        )
+    }
+
+    async fn list_roles(
+        &self,
+        request: Request<ListRolesRequest>,
+    ) -> Result<Response<ListRolesResponse>, Status> {
+        authorize(&request, "/Validate.RoleAdmin/list_roles").await?;
+        let role_names = role_admin::list_roles()
+            .await
+            .map_err(|e| Status::unknown(e.to_string()))?;
+        Ok(Response::new(ListRolesResponse { role_names }))
+    }
+
+    async fn list_role_permissions(
+        &self,
+        request: Request<RoleRequest>,
+    ) -> Result<Response<RolePermissionsResponse>, Status> {
+        authorize(&request, "/Validate.RoleAdmin/list_role_permissions").await?;
+        let permissions = role_admin::list_permissions(&request.into_inner().role_name)
+            .await
+            .map_err(|e| Status::failed_precondition(e.to_string()))?
+            .into_iter()
+            .map(|permission| PbUrlPermission {
+                method: permission.method,
+                url: permission.url,
+            })
+            .collect();
+        Ok(Response::new(RolePermissionsResponse { permissions }))
+    }
+
+    async fn list_role_members(
+        &self,
+        request: Request<RoleRequest>,
+    ) -> Result<Response<RoleMembersResponse>, Status> {
+        authorize(&request, "/Validate.RoleAdmin/list_role_members").await?;
+        let user_names = role_admin::list_members(&request.into_inner().role_name)
+            .await
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+        Ok(Response::new(RoleMembersResponse { user_names }))
+    }
 }
 
 pub async fn grpc_server(addr: &str) {
     let validate = ValidateImpl::default();
-    Server::builder()
+    // 换取 service token 的接口不需要 token
+    let service_auth = ServiceAuthLayer::new(LocalVerifier).skip("/Validate.Validate/client_token");
+    let health = HealthReporter::new();
+    health.set_serving::<ValidateServer<ValidateImpl>>();
+    health.set_serving::<RoleAdminServer<RoleAdminImpl>>();
+    tls::server()
+        .unwrap()
+        .layer(TraceContextLayer)
         .layer(TraceLayer::new_for_grpc())
+        .layer(GrpcMetricsLayer)
+        .layer(service_auth)
+        .add_service(health.server())
         .add_service(ValidateServer::new(validate))
+        .add_service(RoleAdminServer::new(RoleAdminImpl::default()))
         .serve(addr.parse().unwrap())
         .await
         .unwrap();
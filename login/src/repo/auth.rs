@@ -1,16 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::vec;
 
+use anyhow::anyhow;
 use async_trait::async_trait;
 use rbatis::Rbatis;
 use serde::{Deserialize, Serialize};
 
 use table_rbs::CreateTable;
 
+use util::rbatis::init::get_tx_set_defer;
+
 use crate::repo::user::{get_user_by_info, User};
 use crate::repo::DB;
 
+// 注册的用户默认拥有的角色，root > worker > user > normal
+pub const DEFAULT_ROLE: &str = "normal";
+// 管理角色和权限的角色
+pub const ROOT_ROLE: &str = "root";
+
 trait GetRoleID {
     fn get_role_id(&self) -> u64;
 }
@@ -65,7 +73,9 @@ impl InsertIntoRole for Job {
     async fn insert_into_role(&mut self, role_name: &str) -> anyhow::Result<()> {
         let mut rb = DB.clone();
         let rb_caller = &mut rb;
-        self.id = Job::get_or_insert_by_url(rb_caller, &self.url).await?.id;
+        self.id = Job::get_or_insert_by_info(rb_caller, &self.method, &self.url)
+            .await?
+            .id;
         let role = Role::get_or_insert_by_name(rb_caller, role_name).await?;
         RoleBindJob::new(role.id.unwrap(), self.id.unwrap())
             .get_or_insert(rb_caller)
@@ -102,19 +112,6 @@ impl Role {
             role_name: name,
         }
     }
-    pub async fn get_by_job(url: &str) -> HashSet<String> {
-        let mut rb = DB.clone();
-        let vec_job = Job::select_by_column(&mut rb, "url", url)
-            .await
-            .unwrap_or(vec![]);
-        if vec_job.len() == 0 {
-            return HashSet::new();
-        }
-        let role_job = RoleBindJob::select_by_column(&mut rb, "job_id", vec_job[0].id.unwrap())
-            .await
-            .unwrap_or(vec![]);
-        Role::get_role_by_vecid(&mut rb, role_job).await
-    }
     pub async fn get_by_user(user_id: u64) -> HashSet<String> {
         let mut rb = DB.clone();
         let user_role = UserBindRole::select_by_column(&mut rb, "user_id", user_id)
@@ -122,24 +119,74 @@ impl Role {
             .unwrap_or(vec![]);
         Role::get_role_by_vecid(&mut rb, user_role).await
     }
+    // 一次查询所有 id
     async fn get_role_by_vecid(rb: &mut Rbatis, role_ids: Vec<impl GetRoleID>) -> HashSet<String> {
-        let mut result = HashSet::new();
-        let role_id = role_ids.into_iter().map(|bind| bind.get_role_id());
-        for id in role_id {
-            Role::select_by_column(rb, "id", id)
-                .await
-                .unwrap_or(vec![])
-                .into_iter()
-                .for_each(|role| {
-                    result.insert(role.role_name);
-                })
+        let role_ids: Vec<u64> = role_ids.iter().map(|bind| bind.get_role_id()).collect();
+        if role_ids.is_empty() {
+            return HashSet::new();
         }
-        result
+        Role::select_in_column(rb, "id", &role_ids)
+            .await
+            .unwrap_or(vec![])
+            .into_iter()
+            .map(|role| role.role_name)
+            .collect()
     }
     get_fn!(Role;(role_name->role_name_value:&str);Role::new(role_name_value.to_string()));
     async fn get_or_insert_by_name(rb: &mut Rbatis, name: &str) -> anyhow::Result<Self> {
         Role::get_or_insert(rb, "role_name", name).await
     }
+    pub async fn get_by_name(name: &str) -> Option<Self> {
+        Role::get(&mut DB.clone(), "role_name", name).await
+    }
+    pub async fn get_all() -> anyhow::Result<Vec<Self>> {
+        Ok(Role::select_all(&mut DB.clone()).await?)
+    }
+    // 已经存在时返回 false
+    pub async fn create(name: &str) -> anyhow::Result<bool> {
+        let mut rb = DB.clone();
+        if Role::get(&mut rb, "role_name", name).await.is_some() {
+            return Ok(false);
+        }
+        Role::insert(&mut rb, &Role::new(name.to_string())).await?;
+        Ok(true)
+    }
+    // 删除角色以及角色的绑定、继承关系，原来的 child 改为直接继承给它的 parent
+    pub async fn delete(&self) -> anyhow::Result<()> {
+        let role_id = self.id.unwrap_or(0);
+        let mut tx = get_tx_set_defer(DB.clone()).await?;
+        let edges = RoleParent::select_all(&mut tx).await?;
+        for edge in relinked_edges(&edges, role_id) {
+            RoleParent::insert(&mut tx, &edge).await?;
+        }
+        UserBindRole::delete_by_column(&mut tx, "role_id", role_id).await?;
+        RoleBindJob::delete_by_column(&mut tx, "role_id", role_id).await?;
+        RoleParent::delete_by_column(&mut tx, "role_id", role_id).await?;
+        RoleParent::delete_by_column(&mut tx, "parent_id", role_id).await?;
+        Role::delete_by_column(&mut tx, "id", role_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+    pub async fn get_jobs(&self) -> anyhow::Result<Vec<Job>> {
+        let mut rb = DB.clone();
+        let role_job =
+            RoleBindJob::select_by_column(&mut rb, "role_id", self.id.unwrap_or(0)).await?;
+        let job_ids: Vec<u64> = role_job.into_iter().map(|bind| bind.job_id).collect();
+        if job_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(Job::select_in_column(&mut rb, "id", &job_ids).await?)
+    }
+    pub async fn get_users(&self) -> anyhow::Result<Vec<User>> {
+        let mut rb = DB.clone();
+        let user_role =
+            UserBindRole::select_by_column(&mut rb, "role_id", self.id.unwrap_or(0)).await?;
+        let user_ids: Vec<u64> = user_role.into_iter().map(|bind| bind.user_id).collect();
+        if user_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(User::select_in_column(&mut rb, "id", &user_ids).await?)
+    }
 }
 
 #[async_trait]
@@ -158,19 +205,66 @@ impl util::rbatis::init::InitItem for Role {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
 pub struct Job {
     pub id: Option<u64>,
+    // 为空时匹配所有方法
+    pub method: String,
+    // 路由模式，见 domain::job_router
     #[index]
     pub url: String,
 }
 
 rbatis::crud!(Job {});
+rbatis::impl_select!(Job{select_by_info(method:&str,url:&str)->Option
+    =>"`where method = #{method} and url = #{url}`"});
 
 impl Job {
-    pub fn new(url: String) -> Self {
-        Self { id: None, url }
+    pub fn new(method: String, url: String) -> Self {
+        Self {
+            id: None,
+            method,
+            url,
+        }
+    }
+    async fn get_or_insert_by_info(
+        rb: &mut Rbatis,
+        method: &str,
+        url: &str,
+    ) -> anyhow::Result<Self> {
+        if let Some(job) = Job::select_by_info(rb, method, url).await? {
+            return Ok(job);
+        }
+        let mut job = Job::new(method.to_string(), url.to_string());
+        job.id = Job::insert(rb, &job).await?.last_insert_id.as_u64();
+        Ok(job)
+    }
+    pub async fn get_all() -> anyhow::Result<Vec<Self>> {
+        Ok(Job::select_all(&mut DB.clone()).await?)
     }
-    get_fn!(Job;(url->url_value : &str);Job::new(url_value.to_string()));
-    async fn get_or_insert_by_url(rb: &mut Rbatis, url: &str) -> anyhow::Result<Self> {
-        Job::get_or_insert(rb, "url", url).await
+    pub async fn get_by_info(method: &str, url: &str) -> anyhow::Result<Option<Self>> {
+        Ok(Job::select_by_info(&mut DB.clone(), method, url).await?)
+    }
+}
+
+// 旧的 Job 表没有 method 列，建表只会创建不存在的表，这里补上，原有的路由匹配所有方法
+#[async_trait]
+impl util::rbatis::init::InitItem for Job {
+    async fn init() {
+        let rb = DB.clone();
+        let columns: Vec<HashMap<String, String>> = rb
+            .query_decode(
+                "select column_name from information_schema.columns \
+                 where table_schema = database() and table_name = 'Job' and column_name = 'method'",
+                vec![],
+            )
+            .await
+            .expect("init err Job");
+        if columns.is_empty() {
+            rb.exec(
+                "alter table Job add column method varchar(255) not null default ''",
+                vec![],
+            )
+            .await
+            .expect("add column Job.method err");
+        }
     }
 }
 
@@ -187,6 +281,8 @@ impl_get_role_id!(RoleBindJob);
 rbatis::crud!(RoleBindJob {});
 rbatis::impl_select!(RoleBindJob{select_by_info(role_id:u64,job_id:u64)->Option
     =>"`where role_id = #{role_id} and job_id = #{job_id}`"});
+rbatis::impl_delete!(RoleBindJob{delete_by_info(role_id:u64,job_id:u64)
+    =>"`where role_id = #{role_id} and job_id = #{job_id}`"});
 
 impl RoleBindJob {
     fn new(role_id: u64, job_id: u64) -> Self {
@@ -204,6 +300,18 @@ impl RoleBindJob {
         self.id = RoleBindJob::insert(rb, self).await?.last_insert_id.as_u64();
         return Ok(());
     }
+    pub async fn get_all() -> anyhow::Result<Vec<Self>> {
+        Ok(RoleBindJob::select_all(&mut DB.clone()).await?)
+    }
+    // 不存在绑定时返回 false
+    pub async fn delete(role_id: u64, job_id: u64) -> anyhow::Result<bool> {
+        Ok(
+            RoleBindJob::delete_by_info(&mut DB.clone(), role_id, job_id)
+                .await?
+                .rows_affected
+                > 0,
+        )
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
@@ -234,6 +342,8 @@ impl_get_role_id!(UserBindRole);
 rbatis::crud!(UserBindRole {});
 rbatis::impl_select!(UserBindRole{select_by_info(user_id:u64,role_id:u64)->Option
     =>"`where user_id = #{user_id} and role_id = #{role_id}`"});
+rbatis::impl_delete!(UserBindRole{delete_by_info(user_id:u64,role_id:u64)
+    =>"`where user_id = #{user_id} and role_id = #{role_id}`"});
 
 impl UserBindRole {
     fn new(user_id: u64, role_id: u64) -> Self {
@@ -256,4 +366,199 @@ impl UserBindRole {
             .as_u64();
         return Ok(());
     }
+    pub async fn get_role_ids(user_id: u64) -> anyhow::Result<Vec<u64>> {
+        Ok(
+            UserBindRole::select_by_column(&mut DB.clone(), "user_id", user_id)
+                .await?
+                .into_iter()
+                .map(|bind| bind.role_id)
+                .collect(),
+        )
+    }
+    // 不存在绑定时返回 false
+    pub async fn delete(user_id: u64, role_id: u64) -> anyhow::Result<bool> {
+        Ok(
+            UserBindRole::delete_by_info(&mut DB.clone(), user_id, role_id)
+                .await?
+                .rows_affected
+                > 0,
+        )
+    }
+}
+
+// parent 继承 role 的所有权限
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
+pub struct RoleParent {
+    pub id: Option<u64>,
+    #[index]
+    pub role_id: u64,
+    #[index]
+    pub parent_id: u64,
+}
+
+#[async_trait]
+impl util::rbatis::init::InitItem for RoleParent {
+    async fn init() {
+        let role_parents = vec![("worker", "root"), ("user", "worker"), ("normal", "user")];
+        for (role_name, parent_name) in role_parents {
+            RoleParent::set_parent(role_name, parent_name)
+                .await
+                .unwrap_or_else(|e| panic!("init err {} {},{}", role_name, parent_name, e));
+        }
+    }
+}
+
+rbatis::crud!(RoleParent {});
+rbatis::impl_select!(RoleParent{select_by_info(role_id:u64,parent_id:u64)->Option
+    =>"`where role_id = #{role_id} and parent_id = #{parent_id}`"});
+
+impl RoleParent {
+    fn new(role_id: u64, parent_id: u64) -> Self {
+        Self {
+            id: None,
+            role_id,
+            parent_id,
+        }
+    }
+    pub async fn set_parent(role_name: &str, parent_name: &str) -> anyhow::Result<()> {
+        let mut rb = DB.clone();
+        let rb_caller = &mut rb;
+        let role_id = Role::get_or_insert_by_name(rb_caller, role_name)
+            .await?
+            .id
+            .unwrap();
+        let parent_id = Role::get_or_insert_by_name(rb_caller, parent_name)
+            .await?
+            .id
+            .unwrap();
+        if RoleParent::select_by_info(rb_caller, role_id, parent_id)
+            .await?
+            .is_some()
+        {
+            return Ok(());
+        }
+        let edges = RoleParent::select_all(rb_caller).await?;
+        if would_cycle(&edges, role_id, parent_id) {
+            return Err(anyhow!(
+                "{} already inherits {}, can't be its parent",
+                role_name,
+                parent_name
+            ));
+        }
+        RoleParent::insert(rb_caller, &RoleParent::new(role_id, parent_id)).await?;
+        Ok(())
+    }
+    pub async fn get_all() -> anyhow::Result<Vec<Self>> {
+        Ok(RoleParent::select_all(&mut DB.clone()).await?)
+    }
+}
+
+// 从 roles 出发沿着 parent -> role 找到所有继承到的角色，走过的角色不再重复访问，有环也能结束
+pub fn inherited_roles(roles: impl IntoIterator<Item = u64>, edges: &[RoleParent]) -> HashSet<u64> {
+    let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+    for edge in edges {
+        children
+            .entry(edge.parent_id)
+            .or_default()
+            .push(edge.role_id);
+    }
+    let mut result = HashSet::new();
+    let mut stack: Vec<u64> = roles.into_iter().collect();
+    while let Some(role_id) = stack.pop() {
+        if !result.insert(role_id) {
+            continue;
+        }
+        if let Some(children) = children.get(&role_id) {
+            stack.extend(children);
+        }
+    }
+    result
+}
+
+// 添加 parent -> role 之前，role 已经继承了 parent 就会成环
+fn would_cycle(edges: &[RoleParent], role_id: u64, parent_id: u64) -> bool {
+    inherited_roles([role_id], edges).contains(&parent_id)
+}
+
+// 删除 role 时需要补上的 parent -> child，否则 parent 会失去 role 下面所有角色的权限
+fn relinked_edges(edges: &[RoleParent], role_id: u64) -> Vec<RoleParent> {
+    let parents: Vec<u64> = edges
+        .iter()
+        .filter(|edge| edge.role_id == role_id && edge.parent_id != role_id)
+        .map(|edge| edge.parent_id)
+        .collect();
+    let children: Vec<u64> = edges
+        .iter()
+        .filter(|edge| edge.parent_id == role_id && edge.role_id != role_id)
+        .map(|edge| edge.role_id)
+        .collect();
+    let mut result: Vec<RoleParent> = vec![];
+    for &parent_id in &parents {
+        for &child_id in &children {
+            let exists = edges
+                .iter()
+                .chain(&result)
+                .any(|edge| edge.role_id == child_id && edge.parent_id == parent_id);
+            if !exists {
+                result.push(RoleParent::new(child_id, parent_id));
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // root(1) > worker(2) > user(3) > normal(4)
+    fn edges() -> Vec<RoleParent> {
+        vec![
+            RoleParent::new(2, 1),
+            RoleParent::new(3, 2),
+            RoleParent::new(4, 3),
+        ]
+    }
+
+    #[test]
+    fn inherit() {
+        assert_eq!(inherited_roles([1], &edges()), HashSet::from([1, 2, 3, 4]));
+        assert_eq!(inherited_roles([3], &edges()), HashSet::from([3, 4]));
+        assert_eq!(inherited_roles([5], &edges()), HashSet::from([5]));
+
+        let mut cycle = edges();
+        cycle.push(RoleParent::new(1, 4));
+        assert_eq!(inherited_roles([3], &cycle), HashSet::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn cycle() {
+        assert!(would_cycle(&edges(), 1, 4));
+        assert!(would_cycle(&edges(), 1, 1));
+        assert!(!would_cycle(&edges(), 4, 1));
+        assert!(!would_cycle(&edges(), 5, 4));
+    }
+
+    #[test]
+    fn relink() {
+        // 删除 worker 后 root 直接继承 user
+        assert_eq!(relinked_edges(&edges(), 2), vec![RoleParent::new(3, 1)]);
+        // 没有 parent 或 child 时不需要补
+        assert!(relinked_edges(&edges(), 1).is_empty());
+        assert!(relinked_edges(&edges(), 4).is_empty());
+
+        let mut edges = edges();
+        edges.push(RoleParent::new(3, 1));
+        edges.push(RoleParent::new(5, 2));
+        assert_eq!(relinked_edges(&edges, 2), vec![RoleParent::new(5, 1)]);
+        let mut remaining: Vec<RoleParent> = edges
+            .into_iter()
+            .filter(|edge| edge.role_id != 2 && edge.parent_id != 2)
+            .collect();
+        remaining.push(RoleParent::new(5, 1));
+        assert_eq!(
+            inherited_roles([1], &remaining),
+            HashSet::from([1, 3, 4, 5])
+        );
+    }
 }
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use table_rbs::CreateTable;
+
+use crate::repo::user::Result;
+use crate::repo::DB;
+
+// confirmed_at 为 0 表示还没有完成绑定
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
+pub struct UserOtp {
+    pub id: Option<u64>,
+    #[index]
+    pub user_id: u64,
+    pub secret: String,
+    pub confirmed_at: i64,
+}
+
+rbatis::crud!(UserOtp {});
+
+// 只保存 hash，使用后删除
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
+pub struct OtpRecoveryCode {
+    pub id: Option<u64>,
+    #[index]
+    pub user_id: u64,
+    pub code_hash: String,
+}
+
+rbatis::crud!(OtpRecoveryCode {});
+
+impl UserOtp {
+    pub fn new(user_id: u64, secret: String) -> Self {
+        Self {
+            id: None,
+            user_id,
+            secret,
+            confirmed_at: 0,
+        }
+    }
+    pub fn confirmed(&self) -> bool {
+        self.confirmed_at > 0
+    }
+    pub async fn get_by_user(user_id: u64) -> Result<Option<Self>> {
+        Ok(
+            UserOtp::select_by_column(&mut DB.clone(), "user_id", user_id)
+                .await?
+                .pop(),
+        )
+    }
+    pub async fn confirm(&mut self, now: i64) -> Result<()> {
+        self.confirmed_at = now;
+        UserOtp::update_by_column(&mut DB.clone(), self, "id").await?;
+        Ok(())
+    }
+}
+
+// 重新绑定时替换原来的 secret 和恢复码
+pub async fn replace_otp(otp: &mut UserOtp, code_hashes: Vec<String>) -> Result<()> {
+    let rb = DB.clone();
+    let tx_no_defer = rb.acquire_begin().await?;
+    let mut tx = tx_no_defer.defer_async(|mut tx| async move {
+        if !tx.done {
+            if let Err(e) = tx.rollback().await {
+                fast_log::print(format!("defer fun call rollback err {}", e)).unwrap_or(());
+            };
+        }
+    });
+    UserOtp::delete_by_column(&mut tx, "user_id", otp.user_id).await?;
+    OtpRecoveryCode::delete_by_column(&mut tx, "user_id", otp.user_id).await?;
+    otp.id = UserOtp::insert(&mut tx, otp).await?.last_insert_id.as_u64();
+    for code_hash in code_hashes {
+        let code = OtpRecoveryCode {
+            id: None,
+            user_id: otp.user_id,
+            code_hash,
+        };
+        OtpRecoveryCode::insert(&mut tx, &code).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_recovery_codes(user_id: u64) -> Result<Vec<OtpRecoveryCode>> {
+    Ok(OtpRecoveryCode::select_by_column(&mut DB.clone(), "user_id", user_id).await?)
+}
+
+pub async fn delete_recovery_code(id: u64) -> Result<bool> {
+    Ok(OtpRecoveryCode::delete_by_column(&mut DB.clone(), "id", id)
+        .await?
+        .rows_affected
+        == 1)
+}
@@ -1,6 +1,9 @@
 use std::fmt::{Display, Formatter};
 use std::result;
 
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
 use async_trait::async_trait;
 use rbatis::rbdc;
 use serde::{Deserialize, Serialize};
@@ -8,6 +11,9 @@ use thiserror::Error;
 
 use table_rbs::CreateTable;
 
+use crate::config::{PasswordConfig, CONFIG};
+use crate::repo::auth::UserBindRole;
+use crate::repo::otp::{OtpRecoveryCode, UserOtp};
 use crate::repo::DB;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
@@ -43,8 +49,6 @@ impl User {
 }
 
 rbatis::crud!(User {});
-rbatis::impl_select!(User{select_by_info(name:&str,password:&str)->Option
-    =>"`where user_name = #{name} and pass_word = #{password}`"});
 
 pub type Result<T> = result::Result<T, DBExecErr>;
 
@@ -53,6 +57,13 @@ pub enum DBExecErr {
     UserExist,
     UserNotFound,
     ExecErr(#[from] rbdc::Error),
+    HashErr(argon2::password_hash::Error),
+}
+
+impl From<argon2::password_hash::Error> for DBExecErr {
+    fn from(value: argon2::password_hash::Error) -> Self {
+        DBExecErr::HashErr(value)
+    }
 }
 
 impl Display for DBExecErr {
@@ -62,7 +73,7 @@ impl Display for DBExecErr {
 }
 
 pub async fn create_user(user_name: String, pass_word: String) -> Result<User> {
-    let mut user = User::new(user_name, hex::encode(md5::compute(pass_word).to_vec()));
+    let mut user = User::new(user_name, hash_password(&CONFIG.password, &pass_word)?);
     let rb = DB.clone();
     let tx_no_defer = rb.acquire_begin().await?;
     let mut tx = tx_no_defer.defer_async(|mut tx| async move {
@@ -86,20 +97,117 @@ pub async fn create_user(user_name: String, pass_word: String) -> Result<User> {
 
 pub async fn get_user_by_info(user_name: &str, pass_word: Option<&str>) -> Result<User> {
     let mut rb = DB.clone();
-    match pass_word {
-        None => User::select_by_column(&mut rb, "user_name", user_name)
-            .await?
-            .pop()
-            .ok_or_else(|| DBExecErr::UserNotFound),
-
-        Some(pass_word) => User::select_by_info(
-            &mut rb,
-            user_name,
-            &hex::encode(md5::compute(pass_word).to_vec()),
-        )
+    let mut user = User::select_by_column(&mut rb, "user_name", user_name)
+        .await?
+        .pop()
+        .ok_or(DBExecErr::UserNotFound)?;
+    let pass_word = match pass_word {
+        None => return Ok(user),
+        Some(pass_word) => pass_word,
+    };
+    match verify_password(&CONFIG.password, pass_word, &user.pass_word) {
+        Verified::Fail => Err(DBExecErr::UserNotFound),
+        Verified::Ok => Ok(user),
+        Verified::NeedRehash => {
+            // 旧的 md5 或者 cost 变化，登录成功时顺便升级
+            update_password(&mut user, pass_word).await?;
+            Ok(user)
+        }
+    }
+}
+
+pub async fn update_password(user: &mut User, pass_word: &str) -> Result<()> {
+    user.pass_word = hash_password(&CONFIG.password, pass_word)?;
+    User::update_by_column(&mut DB.clone(), user, "id").await?;
+    Ok(())
+}
+
+// 删除用户以及用户绑定的角色、otp
+pub async fn delete_user(user_id: u64) -> Result<()> {
+    let rb = DB.clone();
+    let tx_no_defer = rb.acquire_begin().await?;
+    let mut tx = tx_no_defer.defer_async(|mut tx| async move {
+        if !tx.done {
+            if let Err(e) = tx.rollback().await {
+                fast_log::print(format!("defer fun call rollback err {}", e)).unwrap_or(());
+            };
+        }
+    });
+    UserBindRole::delete_by_column(&mut tx, "user_id", user_id).await?;
+    UserOtp::delete_by_column(&mut tx, "user_id", user_id).await?;
+    OtpRecoveryCode::delete_by_column(&mut tx, "user_id", user_id).await?;
+    if User::delete_by_column(&mut tx, "id", user_id)
         .await?
-        .ok_or_else(|| DBExecErr::UserNotFound),
+        .rows_affected
+        == 0
+    {
+        return Err(DBExecErr::UserNotFound);
     }
+    tx.commit().await?;
+    Ok(())
+}
+
+// otp 恢复码等一次性凭证也使用 argon2 保存
+pub fn hash_secret(secret: &str) -> Result<String> {
+    hash_password(&CONFIG.password, secret)
+}
+
+pub fn verify_secret(secret: &str, stored: &str) -> bool {
+    verify_password(&CONFIG.password, secret, stored) != Verified::Fail
+}
+
+#[derive(Debug, PartialEq)]
+enum Verified {
+    Ok,
+    NeedRehash,
+    Fail,
+}
+
+fn argon2(config: &PasswordConfig) -> Result<Argon2<'static>> {
+    let params = Params::new(
+        config.memory_kib,
+        config.iterations,
+        config.parallelism,
+        None,
+    )
+    .map_err(argon2::password_hash::Error::from)?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+fn hash_password(config: &PasswordConfig, pass_word: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2(config)?
+        .hash_password(pass_word.as_bytes(), &salt)?
+        .to_string())
+}
+
+fn verify_password(config: &PasswordConfig, pass_word: &str, stored: &str) -> Verified {
+    let hash = match PasswordHash::new(stored) {
+        Ok(hash) => hash,
+        // 不是 PHC 字符串，按旧的 hex(md5) 处理
+        Err(_) if hex::encode(md5::compute(pass_word).to_vec()) == stored => {
+            return Verified::NeedRehash
+        }
+        Err(_) => return Verified::Fail,
+    };
+    let argon2 = match argon2(config) {
+        Ok(argon2) => argon2,
+        Err(_) => return Verified::Fail,
+    };
+    if argon2.verify_password(pass_word.as_bytes(), &hash).is_err() {
+        return Verified::Fail;
+    }
+    let same_cost = Params::try_from(&hash)
+        .map(|params| {
+            params.m_cost() == config.memory_kib
+                && params.t_cost() == config.iterations
+                && params.p_cost() == config.parallelism
+        })
+        .unwrap_or(false);
+    if hash.algorithm != argon2::ARGON2ID_IDENT || !same_cost {
+        return Verified::NeedRehash;
+    }
+    Verified::Ok
 }
 
 #[cfg(test)]
@@ -112,6 +220,43 @@ mod test {
 
     use super::*;
 
+    fn test_config() -> PasswordConfig {
+        PasswordConfig {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn hash() {
+        let config = test_config();
+        let hash = hash_password(&config, "333").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_eq!(verify_password(&config, "333", &hash), Verified::Ok);
+        assert_eq!(verify_password(&config, "334", &hash), Verified::Fail);
+
+        let stronger = PasswordConfig {
+            iterations: 2,
+            ..test_config()
+        };
+        assert_eq!(
+            verify_password(&stronger, "333", &hash),
+            Verified::NeedRehash
+        );
+    }
+
+    #[test]
+    fn legacy_md5() {
+        let config = test_config();
+        let legacy = hex::encode(md5::compute("333").to_vec());
+        assert_eq!(
+            verify_password(&config, "333", &legacy),
+            Verified::NeedRehash
+        );
+        assert_eq!(verify_password(&config, "334", &legacy), Verified::Fail);
+    }
+
     #[test]
     fn select() {
         tokio_test::block_on(async {
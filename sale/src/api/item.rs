@@ -1,28 +1,30 @@
 use crate::domain::item::{
     add_item_num as domain_add_item_num, add_to_cart as domain_add_to_cart, change_record_home,
-    create_item, get_all_item, get_all_record_by_user, get_consult_record, root_consult,
-    send_out_record, set_item_num, set_item_price, sign_record, user_consult, wallet_record,
-    Target,
+    consult_record, create_item, get_all_item, get_all_record_by_user, get_consult_record,
+    send_out_record, set_item_num, set_item_price, sign_record, wallet_record, Target,
 };
 
 use crate::repo::item::{Item, Record as repo_record};
 use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
 use util::axum::auth::UserToken;
+use util::axum::scope::RequireScope;
 use util::axum::Response;
 use util::pb::client::get_home_by_id;
+use util::policy::Subject;
+use util::scope::RecordSend;
 
 macro_rules! response {
     ($item:expr) => {{
         match $item {
             Ok(item) => Response::ok(item),
-            Err(e) => Response::err(300, e.to_string()),
+            Err(e) => Response::from_err(e),
         }
     }};
     ($item:expr,$typ:ty) => {{
         match $item {
             Ok(item) => Response::ok(<$typ>::from(item)),
-            Err(e) => Response::err(300, e.to_string()),
+            Err(e) => Response::from_err(e),
         }
     }};
 }
@@ -212,7 +214,8 @@ pub async fn change_home(
 ) -> Response<Record> {
     let mut record: repo_record = req.record.into();
     record.user_id = user.user_id;
-    response!(change_record_home(record, req.home_id).await)
+    let user = Subject::from_token(&user).await;
+    response!(change_record_home(record, req.home_id, user).await)
 }
 
 pub async fn pay(
@@ -221,11 +224,12 @@ pub async fn pay(
 ) -> Response<Record> {
     let mut record: repo_record = req.into();
     record.user_id = user.user_id;
+    let user = Subject::from_token(&user).await;
     response!(wallet_record(record, user, Target::Pay).await)
 }
 
 pub async fn send(
-    Extension(user): Extension<UserToken>,
+    RequireScope(user, _): RequireScope<RecordSend>,
     Json(req): Json<RecordIndex>,
 ) -> Response<Record> {
     let mut record: repo_record = req.into();
@@ -239,6 +243,7 @@ pub async fn sign_to_record(
 ) -> Response<Record> {
     let mut record: repo_record = req.into();
     record.user_id = user.user_id;
+    let user = Subject::from_token(&user).await;
     response!(sign_record(record, user).await)
 }
 
@@ -248,6 +253,7 @@ pub async fn cancel_record(
 ) -> Response<Record> {
     let mut record: repo_record = req.into();
     record.user_id = user.user_id;
+    let user = Subject::from_token(&user).await;
     response!(wallet_record(record, user, Target::Cancel).await)
 }
 
@@ -257,19 +263,8 @@ pub async fn consult(
 ) -> Response<Record> {
     let mut record: repo_record = req.into();
     record.user_id = user.user_id;
-    let response = if user
-        .get_auths()
-        .await
-        .unwrap_or_default()
-        .iter()
-        .find(|auth| auth == &"worker")
-        .is_some()
-    {
-        user_consult(record, user.user_id).await
-    } else {
-        root_consult(record).await
-    };
-    response!(response)
+    let user = Subject::from_token(&user).await;
+    response!(consult_record(record, user).await)
 }
 
 pub async fn get_consult() -> Response<Vec<Record>> {
@@ -6,8 +6,13 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{Mutex, OwnedMutexGuard};
 use tracing::log::{error, warn};
-use util::axum::auth::UserToken;
+use util::metrics::Counter;
 use util::pb::client::{get_home_by_id, operate_wallet, WalletIndex};
+use util::policy::{authorize, Action, Subject};
+
+static ORDERS_PAID: Counter = Counter::new("orders_paid_total", "records paid from the wallet");
+static ORDERS_PAID_AMOUNT: Counter =
+    Counter::new("orders_paid_amount_total", "total price of paid records");
 
 lazy_static! {
     static ref RECORD_OPERATE_LOCK_MAP: Mutex<HashMap<u64, Arc<Mutex<()>>>> =
@@ -74,8 +79,13 @@ pub async fn get_all_item() -> Result<Vec<ItemResponse>> {
         .collect())
 }
 
-pub async fn change_record_home(mut record: Record, home_id: u64) -> Result<RespRecord> {
+pub async fn change_record_home(
+    mut record: Record,
+    home_id: u64,
+    user: Subject,
+) -> Result<RespRecord> {
     record.get_self(None).await?;
+    authorize(&user, Action::Update, &record)?;
     let home = get_home_by_id(home_id).await?;
     if home.user_id != record.user_id {
         return Err(anyhow!("not your home"));
@@ -109,14 +119,16 @@ macro_rules! hashset {
 
 pub async fn wallet_record(
     mut record: Record,
-    user: UserToken,
+    user: Subject,
     target: Target,
 ) -> Result<RespRecord> {
     record.get_self(Some(hashset!("cart", "pay"))).await?;
     let operate = get_record_operate_lock(record.id.unwrap()).await;
-    if record.user_id != user.user_id {
-        return Err(anyhow!("only allow self operate"));
-    }
+    let action = match target {
+        Target::Pay => Action::Pay,
+        Target::Cancel => Action::Cancel,
+    };
+    authorize(&user, action, &record)?;
     let item = Item::select_by_id(record.item_id)
         .await
         .ok_or(anyhow!("get item err"))?;
@@ -134,7 +146,7 @@ pub async fn wallet_record(
         }
     };
 
-    match operate_wallet(WalletIndex::UserID(user.user_id), num, false).await {
+    match operate_wallet(WalletIndex::UserID(record.user_id), num, false).await {
         Err(wallet_err) => {
             warn!("pay err! {},num is {}", wallet_err, num);
             match record.force_change_status(last_status).await {
@@ -152,6 +164,10 @@ pub async fn wallet_record(
         }
         _ => {}
     };
+    if let Target::Pay = target {
+        ORDERS_PAID.inc(&[]);
+        ORDERS_PAID_AMOUNT.add(&[], -num as f64);
+    }
     Ok(RespRecord::new(record, Some(item.name), None).await)
 }
 
@@ -162,38 +178,60 @@ pub async fn send_out_record(mut record: Record) -> Result<RespRecord> {
     Ok(RespRecord::new(record, None, None).await)
 }
 
-pub async fn sign_record(mut record: Record, user: UserToken) -> Result<RespRecord> {
+pub async fn sign_record(mut record: Record, user: Subject) -> Result<RespRecord> {
     record.get_self(Some(hashset!("sending"))).await?;
     let operate = get_record_operate_lock(record.id.unwrap()).await;
-    if record.user_id != user.user_id {
-        if user
-            .get_auths()
-            .await
-            .unwrap_or_default()
-            .iter()
-            .find(|auth| auth.as_str() == "root" || auth.as_str() == "worker")
-            .is_none()
-        {
-            return Err(anyhow!("un auth"));
-        }
-    }
+    authorize(&user, Action::Sign, &record)?;
     record.sign().await?;
     Ok(RespRecord::new(record, None, None).await)
 }
 
-pub async fn user_consult(mut record: Record, user_id: u64) -> Result<RespRecord> {
-    record.get_self(Some(hashset!("sign", "sending"))).await?;
-    let operate = get_record_operate_lock(record.id.unwrap()).await;
-    if record.user_id != user_id {
-        return Err(anyhow!("user_id error"));
+fn consult_action(status: &str) -> Action {
+    match status {
+        "consult" => Action::Discard,
+        _ => Action::Consult,
     }
-    record.consult().await?;
-    Ok(RespRecord::new(record, None, None).await)
 }
 
-pub async fn root_consult(mut record: Record) -> Result<RespRecord> {
-    record.get_self(Some(hashset!("discard"))).await?;
+// 用户对自己签收、配送中的订单申请售后，root 作废售后中的订单
+pub async fn consult_record(mut record: Record, user: Subject) -> Result<RespRecord> {
+    record
+        .get_self(Some(hashset!("sign", "sending", "consult")))
+        .await?;
     let operate = get_record_operate_lock(record.id.unwrap()).await;
-    record.discard().await?;
+    let action = consult_action(&record.status);
+    authorize(&user, action, &record)?;
+    match action {
+        Action::Discard => record.discard().await?,
+        _ => record.consult().await?,
+    }
     Ok(RespRecord::new(record, None, None).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::policy::can;
+
+    #[test]
+    fn consult_transition() {
+        let owner = Subject::new(2, ["normal".to_string()]);
+        let worker = Subject::new(3, ["worker".to_string(), "normal".to_string()]);
+        let root = Subject::new(1, ["root".to_string(), "worker".to_string()]);
+        let mut record = Record {
+            user_id: 2,
+            status: "sign".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(consult_action(&record.status), Action::Consult);
+        assert!(can(&owner, Action::Consult, &record));
+        assert!(!can(&root, Action::Consult, &record));
+
+        // 售后中的订单只有 root 可以作废
+        record.status = "consult".to_string();
+        assert_eq!(consult_action(&record.status), Action::Discard);
+        assert!(can(&root, Action::Discard, &record));
+        assert!(!can(&worker, Action::Discard, &record));
+        assert!(!can(&owner, Action::Discard, &record));
+    }
+}
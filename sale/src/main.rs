@@ -1,8 +1,10 @@
 use axum::routing::{get, post};
 use axum::{middleware, Router};
 use tower_http::trace::TraceLayer;
+use util::health::Readiness;
 
 mod api;
+mod config;
 mod domain;
 mod repo;
 // TODO: discard&consult
@@ -11,15 +13,17 @@ async fn main() {
     // if std::env::var_os("RUST_LOG").is_none() {
     //     std::env::set_var("RUST_LOG", "tower_http=debug,middleware=debug");
     // }
-    util::log_init::init::init();
+    let config = &*config::CONFIG;
+    util::log_init::init::init(&config.log);
+    util::trace::init("sale", &config.trace);
 
     repo::init().await;
-    util::pb::client::init(
-        "http://127.0.0.1:8089".to_string(),
-        "http://127.0.0.1:8090".to_string(),
-        "http://127.0.0.1:8090".to_string(),
-    )
-    .await;
+    util::pb::tls::init(config.tls.clone());
+    util::pb::client::init(&config.peers);
+    util::pb::client::set_client_credentials(
+        config.client.id.clone(),
+        config.client.secret.clone(),
+    );
     init_url_auth().await;
 
     // TODO: 购物车的状态方程，一些零碎的函数
@@ -38,11 +42,18 @@ async fn main() {
         .route("/cancel", post(api::item::cancel_record))
         .route("/consult", post(api::item::consult))
         .route("/show_consult", get(api::item::get_consult))
+        .merge(util::log_init::init::router())
         .layer(middleware::from_fn(util::axum::auth::auth))
         .route("/show_items", get(api::item::show_items))
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(util::axum::metrics::track))
+        .layer(util::trace::TraceContextLayer)
+        .merge(util::metrics::router())
+        .merge(util::health::router(
+            Readiness::new().check("database", repo::ping).peers(),
+        ));
 
-    axum::Server::bind(&"0.0.0.0:8081".parse().unwrap())
+    axum::Server::bind(&config.http_addr.parse().unwrap())
         .serve(app.into_make_service())
         .await
         .unwrap();
@@ -63,6 +74,7 @@ async fn init_url_auth() {
     check_url_auth("/cancel", "normal").await;
     check_url_auth("/consult", "normal").await;
     check_url_auth("/show_consult", "worker").await;
+    // /admin/log_filter 由 login 注册为 root
 }
 
 async fn check_url_auth(url: &str, auth: &str) {
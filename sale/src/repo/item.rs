@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use table_rbs::CreateTable;
 use tracing::log::info;
+use util::policy::{Kind, Resource};
 use util::rbatis::init::get_tx_set_defer;
 
 type Error = anyhow::Result<()>;
@@ -284,6 +285,13 @@ impl Record {
     }
 }
 
+impl Resource for Record {
+    const KIND: Kind = Kind::Record;
+    fn owner_id(&self) -> u64 {
+        self.user_id
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::repo::item::Record;
@@ -1,6 +1,5 @@
 use crate::domain::home::{
-    add_home_addr, change_home_addr, delete_home_addr, get_all as root_get_all, get_all_by_user,
-    Home,
+    add_home_addr, change_home_addr, delete_home_addr, get_all_readable, Home,
 };
 use crate::repo::home::Home as repo_home;
 
@@ -8,6 +7,7 @@ use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
 use util::axum::auth::UserToken;
 use util::axum::Response;
+use util::policy::Subject;
 
 #[derive(Deserialize, Serialize)]
 pub enum AddressIndex {
@@ -78,9 +78,10 @@ pub async fn change_home_address(
     old.user_id = user.user_id;
     let mut new: repo_home = request.new.into();
     new.user_id = user.user_id;
-    match change_home_addr(old, new).await {
+    let subject = Subject::from_token(&user).await;
+    match change_home_addr(old, new, subject).await {
         Ok(home) => Response::ok(Address::new(home, user.user_name)),
-        Err(e) => Response::err(300, e.to_string()),
+        Err(e) => Response::from_err(e),
     }
 }
 
@@ -90,25 +91,16 @@ pub async fn delete_home_address(
 ) -> Response<Address> {
     let mut home: repo_home = request.into();
     home.user_id = user.user_id;
-    match delete_home_addr(home).await {
+    let subject = Subject::from_token(&user).await;
+    match delete_home_addr(home, subject).await {
         Ok(home) => Response::ok(Address::new(home, user.user_name)),
-        Err(e) => Response::err(300, e.to_string()),
+        Err(e) => Response::from_err(e),
     }
 }
 
 pub async fn get_all_address(Extension(user): Extension<UserToken>) -> Response<Vec<Address>> {
-    let addresses = match user.get_auths().await {
-        None => {
-            return Response::err(300, "no auth".to_string());
-        }
-        Some(addresses) => addresses,
-    };
-
-    let homes = match addresses.iter().find(|role| *role == "root") {
-        None => get_all_by_user(user.user_id).await,
-        Some(_) => root_get_all().await,
-    };
-    match homes {
+    let subject = Subject::from_token(&user).await;
+    match get_all_readable(subject).await {
         Ok(addresses) => Response::ok(
             addresses
                 .into_iter()
@@ -4,6 +4,7 @@ use axum::{Extension, Form, Json};
 use serde::{Deserialize, Serialize};
 use util::axum::auth::UserToken;
 use util::axum::Response;
+use util::policy::{authorize, Action, Subject};
 use util::response;
 
 #[derive(Deserialize, Serialize)]
@@ -51,7 +52,13 @@ pub struct RootOperateRequest {
     pub force: bool,
 }
 
-pub async fn root_operate(Json(req): Json<RootOperateRequest>) -> Response<Balance> {
+pub async fn root_operate(
+    Extension(user): Extension<UserToken>,
+    Json(req): Json<RootOperateRequest>,
+) -> Response<Balance> {
     let balance = repo_balance::from_id(req.balance_id);
+    if let Err(e) = authorize(&Subject::from_token(&user).await, Action::Operate, &balance) {
+        return Response::from_err(e);
+    }
     response!(operate(balance, req.num, req.force).await, Balance)
 }
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use util::policy::{authorize, can, Action, Subject};
 
 use crate::repo;
 
@@ -23,14 +24,34 @@ pub async fn add_home_addr(home: repo::home::Home) -> Result<Home> {
     set_home(None, Some(home)).await
 }
 
-pub async fn change_home_addr(old: repo::home::Home, new: repo::home::Home) -> Result<Home> {
+pub async fn change_home_addr(
+    old: repo::home::Home,
+    new: repo::home::Home,
+    user: Subject,
+) -> Result<Home> {
+    check_owner(&old, &user, Action::Update).await?;
     set_home(Some(old), Some(new)).await
 }
 
-pub async fn delete_home_addr(home: repo::home::Home) -> Result<Home> {
+pub async fn delete_home_addr(home: repo::home::Home, user: Subject) -> Result<Home> {
+    check_owner(&home, &user, Action::Delete).await?;
     set_home(Some(home), None).await
 }
 
+// 按 id 操作时地址不一定属于当前用户
+async fn check_owner(home: &repo::home::Home, user: &Subject, action: Action) -> Result<()> {
+    match home.id {
+        Some(id) => {
+            let home = repo::home::Home::select_by_id(id)
+                .await
+                .ok_or(anyhow!("can't get id"))?;
+            authorize(user, action, &home)?
+        }
+        None => authorize(user, action, home)?,
+    };
+    Ok(())
+}
+
 async fn set_home(old: Option<repo::home::Home>, new: Option<repo::home::Home>) -> Result<Home> {
     if old.is_none() && new.is_none() {
         return Err(anyhow!("no request"));
@@ -59,10 +80,12 @@ pub async fn get_all_by_user(user_id: u64) -> Result<Vec<Home>> {
         .collect())
 }
 
-pub async fn get_all() -> Result<Vec<Home>> {
+// 只返回有权限读取的地址
+pub async fn get_all_readable(user: Subject) -> Result<Vec<Home>> {
     Ok(repo::home::Home::get_all()
         .await?
         .into_iter()
+        .filter(|home| can(&user, Action::Read, home))
         .map(|home| Home::from(home))
         .collect())
 }
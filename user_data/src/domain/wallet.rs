@@ -1,5 +1,17 @@
 use crate::repo::wallet::Balance;
 use anyhow::Result;
+use util::metrics::Counter;
+
+static WALLET_DEBITS: Counter = Counter::new("wallet_debits_total", "wallet debits by source");
+static WALLET_DEBIT_AMOUNT: Counter = Counter::new(
+    "wallet_debit_amount_total",
+    "amount debited from wallets by source",
+);
+
+fn debited(source: &str, num: u64) {
+    WALLET_DEBITS.inc(&[("source", source)]);
+    WALLET_DEBIT_AMOUNT.add(&[("source", source)], num as f64);
+}
 
 pub async fn recharge_to_wallet(mut b: Balance, num: u64) -> Result<Balance> {
     b.operate_num(num.try_into()?, false).await?;
@@ -8,10 +20,15 @@ pub async fn recharge_to_wallet(mut b: Balance, num: u64) -> Result<Balance> {
 
 pub async fn cash_out_from_wallet(mut b: Balance, num: u64) -> Result<Balance> {
     b.operate_num(-num.try_into()?, false).await?;
+    debited("cash_out", num);
     Ok(b)
 }
 
+// force 时直接设置余额，不算作扣款
 pub async fn root_operate(mut b: Balance, num: i64, force: bool) -> Result<Balance> {
     b.operate_num(num, force).await?;
+    if num < 0 && !force {
+        debited("operate", num.unsigned_abs());
+    }
     Ok(b)
 }
@@ -1,8 +1,10 @@
 use axum::routing::{get, post};
-use axum::Router;
+use axum::{middleware, Router};
 use tower_http::trace::TraceLayer;
+use util::health::Readiness;
 
 mod api;
+mod config;
 mod domain;
 mod pb;
 mod repo;
@@ -12,15 +14,17 @@ async fn main() {
     // if std::env::var_os("RUST_LOG").is_none() {
     //     std::env::set_var("RUST_LOG", "tower_http=debug,middleware=debug");
     // }
-    util::log_init::init::init();
+    let config = &*config::CONFIG;
+    util::log_init::init::init(&config.log);
+    util::trace::init("user_data", &config.trace);
 
     repo::init().await;
-    util::pb::client::init(
-        "http://127.0.0.1:8089".to_string(),
-        "".to_string(),
-        "".to_string(),
-    )
-    .await;
+    util::pb::tls::init(config.tls.clone());
+    util::pb::client::init(&config.peers);
+    util::pb::client::set_client_credentials(
+        config.client.id.clone(),
+        config.client.secret.clone(),
+    );
     util::pb::init_url_auth(&[
         ("/add_home_address", "normal"),
         ("/change_home_address", "normal"),
@@ -29,10 +33,11 @@ async fn main() {
         ("/recharge_to_balance", "normal"),
         ("/cash_out_from_balance", "normal"),
         ("/root_operate_balance", "root"),
+        // /admin/log_filter 由 login 注册为 root
     ])
     .await;
 
-    tokio::spawn(async move { pb::grpc_server("0.0.0.0:8090") }.await);
+    tokio::spawn(async move { pb::grpc_server(&config.grpc_addr) }.await);
 
     // show_balance
     let app = Router::new()
@@ -43,10 +48,24 @@ async fn main() {
         .route("/recharge_to_balance", post(api::wallet::recharge))
         .route("/cash_out_from_balance", post(api::wallet::cash_out))
         .route("/root_operate_balance", post(api::wallet::root_operate))
-        .layer(util::axum::auth::AuthLayer::new(true, true, false, false))
-        .layer(TraceLayer::new_for_http());
+        .merge(util::log_init::init::router())
+        .layer(
+            util::axum::auth::AuthLayer::new(true, true, false, false).local(
+                config.auth.as_ref().map(|auth| {
+                    util::axum::local_auth::LocalAuth::from_config(auth)
+                        .expect("load local auth err")
+                }),
+            ),
+        )
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(util::axum::metrics::track))
+        .layer(util::trace::TraceContextLayer)
+        .merge(util::metrics::router())
+        .merge(util::health::router(
+            Readiness::new().check("database", repo::ping).peers(),
+        ));
 
-    axum::Server::bind(&"0.0.0.0:8082".parse().unwrap())
+    axum::Server::bind(&config.http_addr.parse().unwrap())
         .serve(app.into_make_service())
         .await
         .unwrap();
@@ -3,6 +3,8 @@ use tonic::{Request, Response, Status};
 use util::pb::home::{
     home_server::Home, GetAllHomeRequest, GetAllHomeResponse, HomeAddress, HomeId,
 };
+use util::pb::require_scope;
+use util::scope::{HomeRead, ScopeName};
 
 #[derive(Debug, Default)]
 pub struct HomeImpl {}
@@ -13,6 +15,7 @@ impl Home for HomeImpl {
         &self,
         request: Request<GetAllHomeRequest>,
     ) -> Result<Response<GetAllHomeResponse>, Status> {
+        require_scope(&request, HomeRead::SCOPE)?;
         let homes = get_all_by_user(request.into_inner().user_id)
             .await
             .map_err(|e| Status::unknown(e.to_string()))?;
@@ -32,6 +35,7 @@ impl Home for HomeImpl {
         &self,
         request: Request<HomeId>,
     ) -> Result<Response<HomeAddress>, Status> {
+        require_scope(&request, HomeRead::SCOPE)?;
         let home = crate::repo::home::Home::select_by_id(request.into_inner().home_id)
             .await
             .ok_or(Status::unknown("invalid id"))?;
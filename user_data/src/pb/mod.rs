@@ -1,8 +1,12 @@
 use home::HomeImpl;
-use tonic::transport::Server;
 use tower_http::trace::TraceLayer;
+use util::health::HealthReporter;
 use util::pb::home::home_server::HomeServer;
+use util::pb::metrics::GrpcMetricsLayer;
+use util::pb::service_auth::{RemoteVerifier, ServiceAuthLayer};
+use util::pb::tls;
 use util::pb::wallet::wallet_server::WalletServer;
+use util::trace::TraceContextLayer;
 use wallet::WalletImpl;
 
 pub mod home;
@@ -11,8 +15,16 @@ pub mod wallet;
 pub async fn grpc_server(addr: &str) {
     let home = HomeImpl::default();
     let wallet = WalletImpl::default();
-    Server::builder()
+    let health = HealthReporter::new();
+    health.set_serving::<HomeServer<HomeImpl>>();
+    health.set_serving::<WalletServer<WalletImpl>>();
+    tls::server()
+        .unwrap()
+        .layer(TraceContextLayer)
         .layer(TraceLayer::new_for_grpc())
+        .layer(GrpcMetricsLayer)
+        .layer(ServiceAuthLayer::new(RemoteVerifier::default()))
+        .add_service(health.server())
         .add_service(HomeServer::new(home))
         .add_service(WalletServer::new(wallet))
         .serve(addr.parse().unwrap())
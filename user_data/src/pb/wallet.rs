@@ -1,8 +1,20 @@
 use crate::domain::wallet::root_operate;
 use crate::repo::wallet::Balance;
 use tonic::{Request, Response, Status};
+use util::pb::require_scope;
 use util::pb::wallet::operate_request::Type;
 use util::pb::wallet::{wallet_server::Wallet, *};
+use util::scope::{ScopeName, WalletAdmin, WalletCredit, WalletDebit, WalletRead};
+
+// 按 id 操作的会直接设置余额
+fn need_scope(typ: Type, num: i64) -> &'static str {
+    match (typ, num) {
+        (Type::BalanceId, _) => WalletAdmin::SCOPE,
+        (_, 0) => WalletRead::SCOPE,
+        (_, num) if num > 0 => WalletCredit::SCOPE,
+        _ => WalletDebit::SCOPE,
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct WalletImpl {}
@@ -13,16 +25,17 @@ impl Wallet for WalletImpl {
         &self,
         request: Request<OperateRequest>,
     ) -> Result<Response<OperateResponse>, Status> {
+        let typ = Type::from_i32(request.get_ref().typ).ok_or(Status::unknown("invalid type"))?;
+        require_scope(&request, need_scope(typ, request.get_ref().num))?;
         let req = request.into_inner();
-        let balance = match Type::from_i32(req.typ) {
-            None => return Err(Status::unknown("invalid type")),
-            Some(Type::UserId) => {
+        let balance = match typ {
+            Type::UserId => {
                 let b = Balance::from_user(req.id);
                 root_operate(b, req.num, false)
                     .await
                     .map_err(|e| Status::unknown(format!("operate err:{e}")))?
             }
-            Some(Type::BalanceId) => {
+            Type::BalanceId => {
                 let b = Balance::from_id(req.id);
                 root_operate(b, req.num, true)
                     .await
@@ -5,6 +5,7 @@ use anyhow::Result;
 use rbatis::{crud, impl_delete, impl_select};
 use serde::{Deserialize, Serialize};
 use table_rbs::CreateTable;
+use util::policy::{Kind, Resource};
 use util::rbatis::init::get_tx_set_defer;
 
 // TODO:所有相关接口
@@ -80,3 +81,10 @@ impl Home {
         Ok(Home::select_all(&mut DB.clone()).await?)
     }
 }
+
+impl Resource for Home {
+    const KIND: Kind = Kind::Home;
+    fn owner_id(&self) -> u64 {
+        self.user_id
+    }
+}
@@ -4,6 +4,7 @@ use anyhow::anyhow;
 use rbatis::crud;
 use serde::{Deserialize, Serialize};
 use table_rbs::CreateTable;
+use util::policy::{Kind, Resource};
 use util::rbatis::init::get_tx_set_defer;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default, CreateTable)]
@@ -67,3 +68,10 @@ impl Balance {
         Ok(())
     }
 }
+
+impl Resource for Balance {
+    const KIND: Kind = Kind::Balance;
+    fn owner_id(&self) -> u64 {
+        self.user_id
+    }
+}
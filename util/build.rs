@@ -14,6 +14,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "proto/validate.proto",
                 "proto/home.proto",
                 "proto/wallet.proto",
+                "proto/health.proto",
             ],
             &["proto"],
         )
@@ -1,7 +1,10 @@
+use crate::axum::local_auth::{read_only, LocalAuth};
+use crate::pb::channel::CallError;
 use crate::pb::client::{get_all_auth, get_all_home, operate_wallet, validate, WalletIndex};
 use crate::pb::home::HomeAddress;
 use crate::pb::validate::UserInfo;
 use crate::pb::wallet::OperateResponse;
+use crate::scope::{self, Scope};
 use anyhow::Result;
 use axum::response::IntoResponse;
 use axum::{
@@ -14,6 +17,7 @@ use axum::{
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tower::{Layer, Service};
 use tracing::info;
@@ -24,6 +28,8 @@ pub struct UserToken {
     pub user_id: u64,
     pub data: HashMap<String, String>,
     pub time_out: i64,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
 }
 
 macro_rules! token_get_fn {
@@ -41,6 +47,9 @@ macro_rules! token_get_fn {
 }
 
 impl UserToken {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        scope::allows(&self.scopes, scope)
+    }
     token_get_fn!(homes -> Option<Vec<Home>> => get_all_home);
     token_get_fn!(auths -> Option<Vec<String>> => get_all_auth);
     token_get_fn!(wallet -> Option<Wallet> => get_wallet);
@@ -91,6 +100,12 @@ impl From<UserInfo> for UserToken {
             user_name: value.user_name,
             data: value.data,
             time_out: value.time_out,
+            // login 签发时已经校验过，解析失败的忽略
+            scopes: value
+                .scopes
+                .iter()
+                .filter_map(|scope| scope.parse().ok())
+                .collect(),
         }
     }
 }
@@ -101,6 +116,8 @@ pub struct AuthLayer {
     pub auth: bool,
     pub home: bool,
     pub wallet: bool,
+    // 本地校验 token，只从 login 获取权限
+    pub local: Option<Arc<LocalAuth>>,
     // TODO:选择到底有哪些额外的组件
 }
 
@@ -112,25 +129,34 @@ impl AuthLayer {
             auth,
             home,
             wallet,
+            local: None,
         }
     }
+
+    // None 时每个请求都通过 login 校验
+    pub fn local(mut self, local: Option<LocalAuth>) -> Self {
+        self.local = local.map(Arc::new);
+        self
+    }
 }
 
 impl<S> Layer<S> for AuthLayer {
     type Service = AuthServer<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        let &AuthLayer {
+        let AuthLayer {
             validate,
             auth,
             home,
             wallet,
-        } = self;
+            local,
+        } = self.clone();
         AuthServer {
             validate,
             auth,
             home,
             wallet,
+            local,
             inner,
         }
     }
@@ -142,6 +168,7 @@ pub struct AuthServer<S> {
     auth: bool,
     home: bool,
     wallet: bool,
+    local: Option<Arc<LocalAuth>>,
     inner: S,
 }
 
@@ -162,12 +189,24 @@ where
     fn call(&mut self, mut request: Request<Body>) -> Self::Future {
         let mut clone = self.clone();
         Box::pin(async move {
-            let mut token = match auth_fn(&request, clone.validate).await {
+            let token = match &clone.local {
+                None => auth_fn(&request, clone.validate).await,
+                Some(local) => local.auth(&request, clone.validate).await,
+            };
+            let mut token = match token {
                 Ok(token) => token,
                 Err(e) => return Ok(e.into_response()),
             };
             if clone.auth {
-                let auths = token.get_auths().await.unwrap_or_default();
+                let auths = match &clone.local {
+                    None => token.get_auths().await,
+                    Some(local) => local
+                        .permissions
+                        .roles(token.user_id, read_only(request.method()))
+                        .await
+                        .ok(),
+                }
+                .unwrap_or_default();
                 token
                     .data
                     .insert("auth".to_string(), serde_json::to_string(&auths).unwrap());
@@ -202,14 +241,26 @@ pub async fn auth_fn(req: &Request<Body>, auth: bool) -> Result<UserToken, Statu
     } else {
         return Err(StatusCode::UNAUTHORIZED);
     };
-    match validate(auth_header.to_string(), req.uri().path().to_string()).await {
+    match validate(
+        auth_header.to_string(),
+        req.method().to_string(),
+        req.uri().path().to_string(),
+    )
+    .await
+    {
         Ok((current_user, ok)) => {
             //可选
             if !auth || ok {
                 return Ok(current_user);
             }
         }
-        Err(e) => info!("{}", e),
+        Err(e) => {
+            info!("{}", e);
+            // login 不可用时不当作未登录
+            if let Some(call_err) = e.downcast_ref::<CallError>() {
+                return Err(StatusCode::from_u16(call_err.code()).unwrap());
+            }
+        }
     }
     Err(StatusCode::UNAUTHORIZED)
 }
@@ -227,8 +278,12 @@ pub async fn auth<B>(mut req: Request<B>, next: Next<B>) -> Result<Response, Sta
         return Err(StatusCode::UNAUTHORIZED);
     };
 
-    if let Ok((current_user, ok)) =
-        validate(auth_header.to_string(), req.uri().path().to_string()).await
+    if let Ok((current_user, ok)) = validate(
+        auth_header.to_string(),
+        req.method().to_string(),
+        req.uri().path().to_string(),
+    )
+    .await
     {
         if ok {
             req.extensions_mut().insert(current_user);
@@ -251,12 +306,11 @@ mod test {
     #[tokio::test]
     async fn layer_test() {
         let str_token = "eyJ1c2VyX25hbWUiOiIzMzMiLCJ1c2VyX2lkIjoxLCJkYXRhIjp7fSwidGltZV9vdXQiOjE2ODE1NDcyNDZ9LmVmOTllNzMwYTczZjU3MTZlMjk5YjllNDgwNmEwMmFmM2JjYzU3MTUyMDNmY2M3OTBlZWI4Nzc3ODZlNDM4Mzc=".to_string();
-        crate::pb::client::init(
-            "http://127.0.0.1:8089".to_string(),
-            "http://127.0.0.1:8090".to_string(),
-            "http://127.0.0.1:8090".to_string(),
-        )
-        .await;
+        crate::pb::client::init(&crate::config::PeersConfig::from_urls(
+            "http://127.0.0.1:8089",
+            "http://127.0.0.1:8090",
+            "http://127.0.0.1:8090",
+        ));
 
         let mut service = ServiceBuilder::new()
             .layer(AuthLayer::new(false, true, true, true))
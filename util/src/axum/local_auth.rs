@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use axum::body::Body;
+use axum::http::{self, Method, Request, StatusCode};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::axum::auth::UserToken;
+use crate::config::{check_url, Validate};
+use crate::pb::channel::CallError;
+use crate::pb::client::{check_revoked, get_all_auth, validate_auth};
+use crate::scope::Scope;
+
+const MAX_CACHED: usize = 10000;
+
+// login 签发的 token 中需要的部分
+#[derive(Deserialize)]
+pub struct Claims {
+    pub user_name: String,
+    pub user_id: u64,
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+    pub exp: i64,
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(default)]
+    pub jti: String,
+    // refresh token 的 family，logout 和 reuse 检测按它撤销
+    #[serde(default)]
+    pub sid: String,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+}
+
+impl From<Claims> for UserToken {
+    fn from(value: Claims) -> Self {
+        Self {
+            user_name: value.user_name,
+            user_id: value.user_id,
+            data: value.data,
+            time_out: value.exp,
+            scopes: value.scopes,
+        }
+    }
+}
+
+type Keys = HashMap<String, (Algorithm, DecodingKey)>;
+
+// 只校验签名、issuer 和过期时间，是否撤销由 LocalAuth 向 login 查询
+pub struct JwtVerifier {
+    issuer: String,
+    // 配置文件中的 key
+    keys: Keys,
+    // 从 login 的 jwks 获取的 key，每次获取后整体替换，login 退役的 key 随之失效
+    jwks: RwLock<Keys>,
+    jwks_url: Option<String>,
+    // 遇到未知的 kid 时才获取，两次获取至少间隔这么久
+    jwks_interval: Duration,
+    jwks_fetched: Mutex<Option<Instant>>,
+}
+
+impl JwtVerifier {
+    pub fn new(issuer: &str) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            keys: HashMap::new(),
+            jwks: Default::default(),
+            jwks_url: None,
+            jwks_interval: Duration::from_secs(default_jwks_refresh()),
+            jwks_fetched: Mutex::new(None),
+        }
+    }
+
+    // HS* 使用和 login 相同的 secret
+    pub fn secret(mut self, kid: &str, algorithm: Algorithm, secret: &[u8]) -> Self {
+        self.keys.insert(
+            kid.to_string(),
+            (algorithm, DecodingKey::from_secret(secret)),
+        );
+        self
+    }
+
+    // RS*/ES*/EdDSA 使用 login 发布的公钥
+    pub fn public_pem(
+        mut self,
+        kid: &str,
+        algorithm: Algorithm,
+        pem: &[u8],
+    ) -> anyhow::Result<Self> {
+        let key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_pem(pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(pem)?,
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(pem)?,
+            _ => return Err(anyhow!("{:?} is not a public key algorithm", algorithm)),
+        };
+        self.keys.insert(kid.to_string(), (algorithm, key));
+        Ok(self)
+    }
+
+    // 例如 http://login:8080/.well-known/jwks.json，只包含非对称算法的 key
+    pub fn jwks(mut self, url: &str, interval: Duration) -> Self {
+        self.jwks_url = Some(url.to_string());
+        self.jwks_interval = interval;
+        self
+    }
+
+    pub fn from_config(config: &LocalAuthConfig) -> anyhow::Result<Self> {
+        let mut verifier = Self::new(&config.issuer);
+        for key in &config.keys {
+            verifier = match (&key.secret, &key.public_key) {
+                (Some(secret), None) => verifier.secret(
+                    &key.kid,
+                    key.algorithm.unwrap_or(Algorithm::HS256),
+                    secret.as_bytes(),
+                ),
+                (None, Some(path)) => {
+                    let pem =
+                        std::fs::read(path).map_err(|e| anyhow!("read {} err,{}", path, e))?;
+                    verifier.public_pem(
+                        &key.kid,
+                        key.algorithm.unwrap_or(Algorithm::RS256),
+                        &pem,
+                    )?
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "auth key {} needs one of secret and public_key",
+                        key.kid
+                    ))
+                }
+            };
+        }
+        if let Some(url) = &config.jwks_url {
+            verifier = verifier.jwks(url, Duration::from_secs(config.jwks_refresh_secs));
+        }
+        Ok(verifier)
+    }
+
+    fn key(&self, kid: &str) -> Option<(Algorithm, DecodingKey)> {
+        match self.keys.get(kid) {
+            Some(key) => Some(key.clone()),
+            None => self.jwks.read().unwrap().get(kid).cloned(),
+        }
+    }
+
+    // 轮换后 login 先发布新 key，未知的 kid 触发一次重新获取
+    async fn refresh_jwks(&self) -> anyhow::Result<()> {
+        let url = match &self.jwks_url {
+            Some(url) => url,
+            None => return Ok(()),
+        };
+        {
+            let mut fetched = self.jwks_fetched.lock().unwrap();
+            if matches!(*fetched, Some(at) if at.elapsed() < self.jwks_interval) {
+                return Ok(());
+            }
+            *fetched = Some(Instant::now());
+        }
+        let response = hyper::Client::new().get(url.parse()?).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("get {} err,{}", url, response.status()));
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        let keys = jwks_keys(&serde_json::from_slice(&body)?);
+        info!("load {} keys from {}", keys.len(), url);
+        *self.jwks.write().unwrap() = keys;
+        Ok(())
+    }
+
+    pub async fn verify(&self, token: &str) -> anyhow::Result<Claims> {
+        let kid = decode_header(token)?
+            .kid
+            .ok_or(anyhow!("token without kid"))?;
+        let (algorithm, key) = match self.key(&kid) {
+            Some(key) => key,
+            None => {
+                if let Err(e) = self.refresh_jwks().await {
+                    warn!("{}", e);
+                }
+                self.key(&kid).ok_or(anyhow!("unknown kid {}", kid))?
+            }
+        };
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+        Ok(decode::<Claims>(token, &key, &validation)?.claims)
+    }
+}
+
+// 没有 kid 或 alg 的 key 无法匹配，跳过
+fn jwks_keys(jwks: &JwkSet) -> Keys {
+    jwks.keys
+        .iter()
+        .filter_map(|jwk| {
+            let kid = jwk.common.key_id.clone()?;
+            let algorithm = jwk.common.algorithm?;
+            let key = DecodingKey::from_jwk(jwk).ok()?;
+            Some((kid, (algorithm, key)))
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocalKeyConfig {
+    pub kid: String,
+    // 默认 secret 使用 HS256，public_key 使用 RS256
+    pub algorithm: Option<Algorithm>,
+    pub secret: Option<String>,
+    // pem 文件路径
+    pub public_key: Option<String>,
+}
+
+// 需要和 login 的 token 配置一致，轮换时先加入新 key，旧 token 全部过期后再删除旧 key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LocalAuthConfig {
+    #[serde(default = "default_issuer")]
+    pub issuer: String,
+    #[serde(default)]
+    pub keys: Vec<LocalKeyConfig>,
+    // login 的 /.well-known/jwks.json，使用非对称算法时可以代替 keys
+    pub jwks_url: Option<String>,
+    #[serde(default = "default_jwks_refresh")]
+    pub jwks_refresh_secs: u64,
+    #[serde(default = "default_permission_ttl")]
+    pub permission_ttl_secs: u64,
+    // logout 等撤销操作在本地最多延迟这么久生效
+    #[serde(default = "default_revocation_ttl")]
+    pub revocation_ttl_secs: u64,
+}
+
+fn default_issuer() -> String {
+    "supermarket-login".to_string()
+}
+
+fn default_jwks_refresh() -> u64 {
+    30
+}
+
+fn default_permission_ttl() -> u64 {
+    60
+}
+
+fn default_revocation_ttl() -> u64 {
+    5
+}
+
+impl Validate for LocalAuthConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.keys.is_empty() && self.jwks_url.is_none() {
+            return Err(anyhow!("auth needs keys or jwks_url"));
+        }
+        for key in &self.keys {
+            if key.secret.is_some() == key.public_key.is_some() {
+                return Err(anyhow!(
+                    "auth key {} needs one of secret and public_key",
+                    key.kid
+                ));
+            }
+        }
+        if let Some(url) = &self.jwks_url {
+            check_url("auth.jwks_url", url, &["http"])?;
+        }
+        Ok(())
+    }
+}
+
+struct Cache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (V, Instant)>>,
+}
+
+impl<K, V> Cache<K, V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+}
+
+// 从 login 获取的权限和撤销状态缓存 ttl，login 不可用时只读请求可以使用过期的缓存
+pub struct PermissionCache {
+    roles: Cache<u64, Vec<String>>,
+    allowed: Cache<(u64, String, String), bool>,
+    revoked: Cache<String, bool>,
+}
+
+impl PermissionCache {
+    pub fn new(ttl: Duration, revocation_ttl: Duration) -> Self {
+        Self {
+            roles: Cache::new(ttl),
+            allowed: Cache::new(ttl),
+            revoked: Cache::new(revocation_ttl),
+        }
+    }
+
+    pub async fn roles(&self, user_id: u64, read_only: bool) -> anyhow::Result<Vec<String>> {
+        self.cached(&self.roles, user_id, read_only, get_all_auth(user_id))
+            .await
+    }
+
+    pub async fn allowed(
+        &self,
+        user_id: u64,
+        method: &str,
+        url: &str,
+        read_only: bool,
+    ) -> anyhow::Result<bool> {
+        let key = (user_id, method.to_string(), url.to_string());
+        let fetch = validate_auth(user_id, method.to_string(), url.to_string());
+        self.cached(&self.allowed, key, read_only, fetch).await
+    }
+
+    // 和 login 的 validate 一样检查 revoked_jti、revoked_sid 和 revoked_before
+    pub async fn revoked(&self, claims: &Claims, read_only: bool) -> anyhow::Result<bool> {
+        let fetch = check_revoked(
+            claims.jti.clone(),
+            claims.sid.clone(),
+            claims.user_id,
+            claims.iat,
+        );
+        self.cached(&self.revoked, claims.jti.clone(), read_only, fetch)
+            .await
+    }
+
+    async fn cached<K, V>(
+        &self,
+        cache: &Cache<K, V>,
+        key: K,
+        stale_ok: bool,
+        fetch: impl Future<Output = anyhow::Result<V>>,
+    ) -> anyhow::Result<V>
+    where
+        K: Hash + Eq,
+        V: Clone,
+    {
+        let ttl = cache.ttl;
+        let stale = match cache.entries.lock().unwrap().get(&key) {
+            Some((value, at)) if at.elapsed() < ttl => return Ok(value.clone()),
+            Some((value, _)) => Some(value.clone()),
+            None => None,
+        };
+        match fetch.await {
+            Ok(value) => {
+                let mut entries = cache.entries.lock().unwrap();
+                if entries.len() >= MAX_CACHED {
+                    entries.retain(|_, (_, at)| at.elapsed() < ttl);
+                }
+                if entries.len() >= MAX_CACHED {
+                    entries.clear();
+                }
+                entries.insert(key, (value.clone(), Instant::now()));
+                Ok(value)
+            }
+            Err(e) => match stale {
+                Some(value) if stale_ok => {
+                    warn!("fetch permission err,{}, use stale cache", e);
+                    Ok(value)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+pub struct LocalAuth {
+    pub verifier: JwtVerifier,
+    pub permissions: PermissionCache,
+}
+
+fn status(e: anyhow::Error) -> StatusCode {
+    warn!("{}", e);
+    let code = e.downcast_ref::<CallError>().map_or(503, CallError::code);
+    StatusCode::from_u16(code).unwrap()
+}
+
+impl LocalAuth {
+    pub fn new(verifier: JwtVerifier, ttl: Duration, revocation_ttl: Duration) -> Self {
+        Self {
+            verifier,
+            permissions: PermissionCache::new(ttl, revocation_ttl),
+        }
+    }
+
+    pub fn from_config(config: &LocalAuthConfig) -> anyhow::Result<Self> {
+        Ok(Self::new(
+            JwtVerifier::from_config(config)?,
+            Duration::from_secs(config.permission_ttl_secs),
+            Duration::from_secs(config.revocation_ttl_secs),
+        ))
+    }
+
+    pub async fn auth(&self, req: &Request<Body>, validate: bool) -> Result<UserToken, StatusCode> {
+        let token = req
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let claims = self.verifier.verify(token).await.map_err(|e| {
+            info!("{}", e);
+            StatusCode::UNAUTHORIZED
+        })?;
+        let read_only = read_only(req.method());
+        if self
+            .permissions
+            .revoked(&claims, read_only)
+            .await
+            .map_err(status)?
+        {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        let user: UserToken = claims.into();
+        if !validate {
+            return Ok(user);
+        }
+        let allowed = self
+            .permissions
+            .allowed(
+                user.user_id,
+                req.method().as_str(),
+                req.uri().path(),
+                read_only,
+            )
+            .await
+            .map_err(status)?;
+        match allowed {
+            true => Ok(user),
+            false => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+pub fn read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn sign(kid: &str, secret: &str, iss: &str, exp_offset: i64) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(kid.to_string());
+        let claims = json!({
+            "iss": iss,
+            "sub": "3",
+            "iat": now,
+            "exp": now + exp_offset,
+            "jti": "jti",
+            "sid": "family",
+            "user_name": "worker",
+            "user_id": 3,
+            "data": {},
+            "scopes": ["record:send"],
+        });
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn verify() {
+        let verifier =
+            JwtVerifier::new("supermarket-login").secret("dev", Algorithm::HS256, b"secret");
+
+        let claims = verifier
+            .verify(&sign("dev", "secret", "supermarket-login", 3600))
+            .await
+            .unwrap();
+        assert_eq!(claims.jti, "jti");
+        assert_eq!(claims.sid, "family");
+        let user: UserToken = claims.into();
+        assert_eq!(user.user_id, 3);
+        assert_eq!(user.user_name, "worker");
+        assert!(user.has_scope("record:send"));
+
+        for token in [
+            sign("dev", "secret", "supermarket-login", -3600),
+            sign("dev", "other", "supermarket-login", 3600),
+            sign("other", "secret", "supermarket-login", 3600),
+            sign("dev", "secret", "other", 3600),
+        ] {
+            assert!(verifier.verify(&token).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn rotate() {
+        let config: LocalAuthConfig = toml::from_str(
+            r#"
+            [[keys]]
+            kid = "old"
+            secret = "old-secret"
+            [[keys]]
+            kid = "new"
+            secret = "new-secret"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let verifier = JwtVerifier::from_config(&config).unwrap();
+        // 轮换期间新旧 key 签发的 token 都有效
+        for (kid, secret) in [("old", "old-secret"), ("new", "new-secret")] {
+            assert!(verifier
+                .verify(&sign(kid, secret, "supermarket-login", 3600))
+                .await
+                .is_ok());
+        }
+        assert!(verifier
+            .verify(&sign("old", "new-secret", "supermarket-login", 3600))
+            .await
+            .is_err());
+
+        let empty: LocalAuthConfig = toml::from_str("").unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let permissions =
+            PermissionCache::new(Duration::from_millis(50), Duration::from_millis(50));
+        let cache = &permissions.roles;
+        let roles = || async { Ok(vec!["normal".to_string()]) };
+        let down = || async { Err::<Vec<String>, _>(anyhow!("login down")) };
+
+        assert_eq!(
+            permissions.cached(cache, 1, false, roles()).await.unwrap(),
+            vec!["normal"]
+        );
+        // 没有过期时不会请求 login
+        assert!(permissions.cached(cache, 1, false, down()).await.is_ok());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(permissions.cached(cache, 1, false, down()).await.is_err());
+        assert_eq!(
+            permissions.cached(cache, 1, true, down()).await.unwrap(),
+            vec!["normal"]
+        );
+        assert!(permissions.cached(cache, 2, true, down()).await.is_err());
+
+        // 撤销状态没有缓存时 login 不可用则拒绝
+        let revoked = &permissions.revoked;
+        let active = || async { Ok(false) };
+        let down = || async { Err::<bool, _>(anyhow!("login down")) };
+        assert!(permissions
+            .cached(revoked, "jti".to_string(), true, down())
+            .await
+            .is_err());
+        assert!(!permissions
+            .cached(revoked, "jti".to_string(), false, active())
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::Instant;
+
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::metrics::{Counter, Histogram, LATENCY_BUCKETS};
+
+static HTTP_REQUESTS: Counter = Counter::new(
+    "http_requests_total",
+    "HTTP requests by method, route and status",
+);
+static HTTP_DURATION: Histogram = Histogram::new(
+    "http_request_duration_seconds",
+    "HTTP request latency by method, route and status",
+    LATENCY_BUCKETS,
+);
+
+// 通过 Router::layer 添加，只统计之前添加的路由
+// 用路由的模板作为 label，没有匹配的统一记为 unmatched，避免 label 过多
+pub async fn track<B>(req: Request<B>, next: Next<B>) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or("unmatched".to_string());
+    let method = req.method().clone();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let status = response.status().as_u16().to_string();
+    let labels = [
+        ("method", method.as_str()),
+        ("route", route.as_str()),
+        ("status", status.as_str()),
+    ];
+    HTTP_REQUESTS.inc(&labels);
+    HTTP_DURATION.observe(&labels, start.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::render;
+    use axum::body::Body;
+    use axum::middleware;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn routes() {
+        let app = Router::new()
+            .route("/item/:id", get(|| async { "item" }))
+            .layer(middleware::from_fn(track));
+        for uri in ["/item/1", "/item/2", "/missing"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+        let text = render();
+        assert!(text
+            .contains("http_requests_total{method=\"GET\",route=\"/item/:id\",status=\"200\"} 2"));
+        assert!(!text.contains("/missing"));
+    }
+}
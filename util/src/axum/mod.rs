@@ -2,20 +2,27 @@ use axum::response::IntoResponse;
 use axum::Json;
 use serde::Serialize;
 
+use crate::pb::channel::CallError;
+use crate::policy::Forbidden;
+use crate::trace;
+
 pub mod auth;
+pub mod local_auth;
+pub mod metrics;
+pub mod scope;
 
 #[macro_export]
 macro_rules! response {
     ($item:expr) => {{
         match $item {
             Ok(item) => Response::ok(item),
-            Err(e) => Response::err(300, e.to_string()),
+            Err(e) => Response::from_err(e),
         }
     }};
     ($item:expr,$typ:ty) => {{
         match $item {
             Ok(item) => Response::ok(<$typ>::from(item)),
-            Err(e) => Response::err(300, e.to_string()),
+            Err(e) => Response::from_err(e),
         }
     }};
 }
@@ -25,6 +32,9 @@ pub struct Response<T: Serialize> {
     pub code: i32,
     pub msg: String,
     pub data: Option<T>,
+    // 和响应头中的 x-request-id 一致，方便按日志排查
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl<T> Response<T>
@@ -32,7 +42,13 @@ where
     T: Serialize,
 {
     pub fn new(code: i32, msg: String, data: Option<T>) -> Self {
-        Self { code, msg, data }
+        let request_id = trace::current().map(|ctx| ctx.request_id);
+        Self {
+            code,
+            msg,
+            data,
+            request_id,
+        }
     }
     pub fn ok(data: T) -> Self {
         Self::new(200, "OK".to_string(), Some(data))
@@ -40,6 +56,17 @@ where
     pub fn err(code: i32, msg: String) -> Self {
         Self::new(code, msg, None)
     }
+    // 没有权限的统一返回 403，下游服务熔断返回 503，超时返回 504
+    pub fn from_err(e: impl Into<anyhow::Error>) -> Self {
+        let e = e.into();
+        if e.downcast_ref::<Forbidden>().is_some() {
+            return Self::err(403, e.to_string());
+        }
+        match e.downcast_ref::<CallError>() {
+            Some(call_err) => Self::err(call_err.code() as i32, e.to_string()),
+            None => Self::err(300, e.to_string()),
+        }
+    }
 }
 
 impl<T> IntoResponse for Response<T>
@@ -0,0 +1,35 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use tracing::info;
+
+use crate::axum::auth::UserToken;
+use crate::scope::ScopeName;
+
+// 需要经过 auth 中间件，token 中没有对应 scope 时返回 403
+//   async fn send(RequireScope(user, _): RequireScope<RecordSend>) {}
+pub struct RequireScope<S: ScopeName>(pub UserToken, pub PhantomData<S>);
+
+#[async_trait]
+impl<S, St> FromRequestParts<St> for RequireScope<S>
+where
+    S: ScopeName,
+    St: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &St) -> Result<Self, Self::Rejection> {
+        let user = parts
+            .extensions
+            .get::<UserToken>()
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        if !user.has_scope(S::SCOPE) {
+            info!("{} missing scope {}", user.user_name, S::SCOPE);
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(Self(user.clone(), PhantomData))
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use futures::future::{join_all, BoxFuture};
+use futures::Stream;
+use tokio::sync::watch;
+use tonic::server::NamedService;
+use tonic::{Request, Response, Status};
+
+use crate::axum::Response as ApiResponse;
+use crate::pb::channel::ServiceChannel;
+use crate::pb::client::clients;
+use crate::pb::health::health_check_response::ServingStatus;
+use crate::pb::health::health_client::HealthClient;
+use crate::pb::health::health_server::{Health, HealthServer};
+use crate::pb::health::{HealthCheckRequest, HealthCheckResponse};
+
+// 单个依赖检查的超时，超时算作不可用
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Check = Arc<dyn Fn() -> BoxFuture<'static, anyhow::Result<()>> + Send + Sync>;
+
+// /readyz 需要检查的依赖，全部可用时返回 200，否则返回 503
+#[derive(Clone, Default)]
+pub struct Readiness {
+    checks: Vec<(String, Check)>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check<F, Fut>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.checks
+            .push((name.to_string(), Arc::new(move || Box::pin(check()))));
+        self
+    }
+
+    // 检查配置了地址的下游 grpc 服务，需要在 client::init 之后调用
+    pub fn peers(self) -> Self {
+        let clients = clients();
+        [
+            ("validate", clients.validate),
+            ("home", clients.home),
+            ("wallet", clients.wallet),
+        ]
+        .into_iter()
+        .filter_map(|(name, channel)| Some((name, channel?)))
+        .fold(self, |readiness, (name, channel)| {
+            readiness.check(&format!("grpc_{}", name), move || {
+                check_peer(channel.clone())
+            })
+        })
+    }
+
+    pub async fn run(&self) -> BTreeMap<String, anyhow::Result<()>> {
+        let results = join_all(self.checks.iter().map(|(name, check)| async move {
+            let result = match tokio::time::timeout(CHECK_TIMEOUT, check()).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow!("timeout after {:?}", CHECK_TIMEOUT)),
+            };
+            (name.clone(), result)
+        }))
+        .await;
+        results.into_iter().collect()
+    }
+}
+
+// 下游服务的 grpc.health.v1，不需要 service token
+async fn check_peer(channel: ServiceChannel) -> anyhow::Result<()> {
+    let status = HealthClient::new(channel.channel())
+        .check(HealthCheckRequest::default())
+        .await?
+        .into_inner()
+        .status;
+    match status == ServingStatus::Serving as i32 {
+        true => Ok(()),
+        false => Err(anyhow!("status {}", status)),
+    }
+}
+
+// /healthz 只表示进程存活，/readyz 检查依赖，都不需要登录
+pub fn router(readiness: Readiness) -> Router {
+    Router::new()
+        .route("/healthz", get(|| async { ApiResponse::ok("alive") }))
+        .route(
+            "/readyz",
+            get(move || {
+                let readiness = readiness.clone();
+                async move { ready(&readiness).await }
+            }),
+        )
+}
+
+async fn ready(readiness: &Readiness) -> (StatusCode, ApiResponse<BTreeMap<String, String>>) {
+    let results = readiness.run().await;
+    let ready = results.values().all(|result| result.is_ok());
+    let data = results
+        .into_iter()
+        .map(|(name, result)| match result {
+            Ok(_) => (name, "ok".to_string()),
+            Err(e) => (name, format!("{:#}", e)),
+        })
+        .collect();
+    match ready {
+        true => (StatusCode::OK, ApiResponse::ok(data)),
+        false => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiResponse::new(503, "not ready".to_string(), Some(data)),
+        ),
+    }
+}
+
+// grpc 服务端的健康状态，空字符串表示整个 server
+#[derive(Clone)]
+pub struct HealthReporter {
+    statuses: Arc<watch::Sender<HashMap<String, ServingStatus>>>,
+}
+
+impl Default for HealthReporter {
+    fn default() -> Self {
+        let statuses = HashMap::from([(String::new(), ServingStatus::Serving)]);
+        Self {
+            statuses: Arc::new(watch::channel(statuses).0),
+        }
+    }
+}
+
+impl HealthReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_status(&self, service: &str, status: ServingStatus) {
+        self.statuses.send_modify(|statuses| {
+            statuses.insert(service.to_string(), status);
+        });
+    }
+
+    pub fn set_serving<S: NamedService>(&self) {
+        self.set_status(S::NAME, ServingStatus::Serving);
+    }
+
+    pub fn set_not_serving<S: NamedService>(&self) {
+        self.set_status(S::NAME, ServingStatus::NotServing);
+    }
+
+    fn status(&self, service: &str) -> Option<ServingStatus> {
+        self.statuses.borrow().get(service).copied()
+    }
+
+    pub fn server(&self) -> HealthServer<HealthReporter> {
+        HealthServer::new(self.clone())
+    }
+}
+
+type WatchStream = Pin<Box<dyn Stream<Item = Result<HealthCheckResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Health for HealthReporter {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        match self.status(&service) {
+            Some(status) => Ok(Response::new(HealthCheckResponse {
+                status: status as i32,
+            })),
+            None => Err(Status::not_found(format!("unknown service {}", service))),
+        }
+    }
+
+    type WatchStream = WatchStream;
+
+    // 先返回当前状态，之后状态变化时再返回
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let receiver = self.statuses.subscribe();
+        let stream = futures::stream::unfold(
+            (receiver, None),
+            move |(mut receiver, last): (_, Option<ServingStatus>)| {
+                let service = service.clone();
+                async move {
+                    loop {
+                        let status = receiver
+                            .borrow_and_update()
+                            .get(&service)
+                            .copied()
+                            .unwrap_or(ServingStatus::ServiceUnknown);
+                        if last != Some(status) {
+                            let response = HealthCheckResponse {
+                                status: status as i32,
+                            };
+                            return Some((Ok(response), (receiver, Some(status))));
+                        }
+                        receiver.changed().await.ok()?;
+                    }
+                }
+            },
+        );
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http;
+    use futures::StreamExt;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn ready() {
+        let app = router(
+            Readiness::new()
+                .check("database", || async { Ok(()) })
+                .check("redis", || async { Err(anyhow!("connection refused")) }),
+        );
+        let call = |uri: &str| {
+            app.clone()
+                .oneshot(http::Request::get(uri).body(Body::empty()).unwrap())
+        };
+        assert_eq!(call("/healthz").await.unwrap().status(), StatusCode::OK);
+        let response = call("/readyz").await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["database"], "ok");
+        assert_eq!(body["data"]["redis"], "connection refused");
+
+        let app = router(Readiness::new().check("database", || async { Ok(()) }));
+        let response = app
+            .oneshot(http::Request::get("/readyz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn grpc() {
+        let reporter = HealthReporter::new();
+        reporter.set_status("Home.Home", ServingStatus::Serving);
+        let check = |service: &str| {
+            reporter.check(Request::new(HealthCheckRequest {
+                service: service.to_string(),
+            }))
+        };
+        let status = check("").await.unwrap().into_inner().status;
+        assert_eq!(status, ServingStatus::Serving as i32);
+        assert_eq!(
+            check("Other").await.unwrap_err().code(),
+            tonic::Code::NotFound
+        );
+
+        let mut stream = reporter
+            .watch(Request::new(HealthCheckRequest {
+                service: "Home.Home".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let next = |status: ServingStatus| Some(status as i32);
+        let status = stream.next().await.map(|r| r.unwrap().status);
+        assert_eq!(status, next(ServingStatus::Serving));
+        reporter.set_status("Home.Home", ServingStatus::NotServing);
+        let status = stream.next().await.map(|r| r.unwrap().status);
+        assert_eq!(status, next(ServingStatus::NotServing));
+    }
+}
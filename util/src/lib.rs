@@ -1,4 +1,10 @@
 pub mod axum;
+pub mod config;
+pub mod health;
 pub mod log_init;
+pub mod metrics;
 pub mod pb;
+pub mod policy;
+pub mod scope;
+pub mod trace;
 pub mod rbatis;
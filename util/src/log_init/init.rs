@@ -1,31 +1,240 @@
+use std::env;
+use std::sync::RwLock;
+
+use anyhow::anyhow;
+use axum::routing::get;
+use axum::{Json, Router};
 use clap::Parser;
-use std::fs::File;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+use crate::axum::Response;
+use crate::config::Validate;
+use crate::log_init::rolling::RollingFile;
+
+lazy_static! {
+    pub static ref ARGS: Args = Args::parse();
+    // init 之后才有，用于运行时修改日志级别
+    static ref FILTER: RwLock<Option<reload::Handle<EnvFilter, Registry>>> = RwLock::new(None);
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Name of the person to greet
+    /// Log file, overrides log.file in the config
     #[arg(short, long)]
     pub rust_log_file: Option<String>,
+    /// Config file, defaults to <service>/config.toml
+    #[arg(short, long)]
+    pub config: Option<String>,
+    /// Override a config value, e.g. --set peers.home.timeout_ms=1000
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // 单行文本
+    Text,
+    // 多行，适合本地开发
+    Pretty,
+    // 每行一个 json，带上当前 span 的 trace_id 和 request_id
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LogConfig {
+    // EnvFilter 的语法，例如 info,tower_http=debug，设置了 RUST_LOG 时以 RUST_LOG 为准
+    pub filter: String,
+    pub format: LogFormat,
+    // 不配置时输出到 stdout
+    pub file: Option<String>,
+    // 每天切分一次，超过 max_size_mb 时也切分，0 表示不按大小切分
+    pub daily: bool,
+    pub max_size_mb: u64,
+    // 保留的旧文件数，0 表示全部保留
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "info,tower_http=debug".to_string(),
+            format: LogFormat::Text,
+            file: None,
+            daily: true,
+            max_size_mb: 100,
+            max_files: 7,
+        }
+    }
+}
+
+impl Validate for LogConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        EnvFilter::try_new(&self.filter).map_err(|e| anyhow!("log.filter {}", e))?;
+        Ok(())
+    }
+}
+
+fn env_filter(config: &LogConfig) -> EnvFilter {
+    match env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(filter) => EnvFilter::try_new(filter).expect("invalid RUST_LOG"),
+        Err(_) => EnvFilter::new(&config.filter),
+    }
+}
+
+fn writer(config: &LogConfig) -> BoxMakeWriter {
+    match ARGS.rust_log_file.as_ref().or(config.file.as_ref()) {
+        Some(file) => {
+            let max_size = config.max_size_mb * 1024 * 1024;
+            let rolling = RollingFile::new(file, config.daily, max_size, config.max_files)
+                .unwrap_or_else(|e| panic!("open log file {} err,{}", file, e));
+            BoxMakeWriter::new(rolling)
+        }
+        None => BoxMakeWriter::new(std::io::stdout),
+    }
 }
 
-pub fn init() {
-    let args = Args::parse();
-    if args.rust_log_file.is_some() {
-        let file = args.rust_log_file.unwrap();
-        let out = File::options()
-            .write(true)
-            .create(true)
-            .append(false)
-            .open(file)
-            .expect("impossible");
-        tracing_subscriber::fmt()
-            .with_writer(out)
-            .with_max_level(tracing::Level::DEBUG)
-            .init();
-    } else {
-        tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::DEBUG)
-            .init();
+fn fmt_layer<S>(format: LogFormat, writer: BoxMakeWriter) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
+
+// 需要在读取配置之后调用
+pub fn init(config: &LogConfig) {
+    let (filter, handle) = reload::Layer::new(env_filter(config));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(config.format, writer(config)))
+        .init();
+    *FILTER.write().unwrap() = Some(handle);
+}
+
+pub fn filter() -> anyhow::Result<String> {
+    let handle = FILTER.read().unwrap().clone();
+    let handle = handle.ok_or(anyhow!("log not init"))?;
+    Ok(handle.with_current(|filter| filter.to_string())?)
+}
+
+// 只影响当前进程，重启后恢复为配置中的值
+pub fn set_filter(filter: &str) -> anyhow::Result<String> {
+    let new = EnvFilter::try_new(filter)?;
+    let handle = FILTER.read().unwrap().clone();
+    handle.ok_or(anyhow!("log not init"))?.reload(new)?;
+    tracing::info!("log filter changed to {}", filter);
+    self::filter()
+}
+
+#[derive(Deserialize)]
+pub struct FilterRequest {
+    pub filter: String,
+}
+
+// 需要放在鉴权的 layer 之前，只允许 root 调用
+pub fn router() -> Router {
+    Router::new().route(
+        "/admin/log_filter",
+        get(|| async { crate::response!(filter()) }).put(
+            |Json(request): Json<FilterRequest>| async move {
+                crate::response!(set_filter(&request.filter))
+            },
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reload_filter() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry().with(layer).with(fmt_layer(
+            LogFormat::Json,
+            BoxMakeWriter::new(move || writer.clone()),
+        ));
+        // handle 只持有弱引用，dispatch 需要一直存在
+        let dispatch = tracing::Dispatch::new(subscriber);
+        *FILTER.write().unwrap() = Some(handle);
+
+        let app = router();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::put("/admin/log_filter")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"filter":"info,util=debug"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], 200);
+        assert_eq!(filter().unwrap(), body["data"]);
+
+        tracing::dispatcher::with_default(&dispatch, || {
+            tracing::debug!(target: "util::pay", order = 1, "shown");
+            tracing::debug!(target: "sale", "hidden");
+        });
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["fields"]["message"], "shown");
+        assert_eq!(lines[0]["fields"]["order"], 1);
+
+        let response = app
+            .oneshot(
+                Request::put("/admin/log_filter")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"filter":"info,[=bad"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], 300);
+        assert_eq!(filter().unwrap(), "util=debug,info");
     }
 }
@@ -0,0 +1,201 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local, NaiveDate};
+use tracing_subscriber::fmt::MakeWriter;
+
+// 一直写入 path，切分时把当前文件重命名为 path.<时间>，只保留最近 max_files 个
+pub struct RollingFile {
+    path: PathBuf,
+    daily: bool,
+    // 0 表示不按大小切分
+    max_size: u64,
+    // 0 表示不删除旧文件
+    max_files: usize,
+    state: Mutex<State>,
+}
+
+struct State {
+    file: File,
+    size: u64,
+    day: NaiveDate,
+}
+
+fn open(path: &Path) -> io::Result<State> {
+    // 重启时追加写入，不覆盖之前的日志
+    let file = File::options().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let modified: DateTime<Local> = metadata.modified()?.into();
+    Ok(State {
+        file,
+        size: metadata.len(),
+        day: modified.date_naive(),
+    })
+}
+
+impl RollingFile {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        daily: bool,
+        max_size: u64,
+        max_files: usize,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let state = open(&path)?;
+        Ok(Self {
+            path,
+            daily,
+            max_size,
+            max_files,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn should_roll(&self, state: &State, now: DateTime<Local>, len: usize) -> bool {
+        if state.size == 0 {
+            return false;
+        }
+        (self.daily && state.day != now.date_naive())
+            || (self.max_size > 0 && state.size + len as u64 > self.max_size)
+    }
+
+    fn roll(&self, state: &mut State, now: DateTime<Local>) -> io::Result<()> {
+        state.file.flush()?;
+        // 按天切分时用文件所属的那一天，按大小切分时用当前时间，重名时加序号
+        let stamp = match self.daily && state.day != now.date_naive() {
+            true => state.day.format("%Y-%m-%d").to_string(),
+            false => now.format("%Y-%m-%d-%H%M%S").to_string(),
+        };
+        let mut target = self.rolled(&stamp);
+        let mut n = 1;
+        while target.exists() {
+            target = self.rolled(&format!("{}.{}", stamp, n));
+            n += 1;
+        }
+        fs::rename(&self.path, target)?;
+        *state = open(&self.path)?;
+        state.day = now.date_naive();
+        self.cleanup()
+    }
+
+    fn rolled(&self, suffix: &str) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(suffix);
+        self.path.with_file_name(name)
+    }
+
+    // 切分出来的文件名带时间，按修改时间删除最旧的
+    pub fn rolled_files(&self) -> io::Result<Vec<PathBuf>> {
+        let prefix = match self.path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("{}.", name),
+            None => return Ok(vec![]),
+        };
+        let dir = match self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("."),
+        };
+        let mut files = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_str().is_some_and(|name| name.starts_with(&prefix)) {
+                files.push((entry.metadata()?.modified()?, entry.path()));
+            }
+        }
+        files.sort();
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    fn cleanup(&self) -> io::Result<()> {
+        if self.max_files == 0 {
+            return Ok(());
+        }
+        let files = self.rolled_files()?;
+        let remove = files.len().saturating_sub(self.max_files);
+        for path in &files[..remove] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn write_at(&self, buf: &[u8], now: DateTime<Local>) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if self.should_roll(&state, now, buf.len()) {
+            self.roll(&mut state, now)?;
+        }
+        let n = state.file.write(buf)?;
+        state.size += n as u64;
+        Ok(n)
+    }
+}
+
+pub struct RollingWriter<'a>(&'a RollingFile);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_at(buf, Local::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.state.lock().unwrap().file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn roll() {
+        let dir = std::env::temp_dir().join("supermarket-rolling-test");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("sale.log");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "before restart\n").unwrap();
+
+        let rolling = RollingFile::new(&path, true, 32, 2).unwrap();
+        let now = Local::now();
+        rolling.write_at(b"0123456789\n", now).unwrap();
+        // 重启后追加写入
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("before restart\n"));
+
+        // 超过大小时切分
+        rolling.write_at(b"0123456789\n", now).unwrap();
+        assert_eq!(rolling.rolled_files().unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0123456789\n");
+
+        // 跨天时切分，文件名是前一天的日期
+        rolling
+            .write_at(b"tomorrow\n", now + Duration::days(1))
+            .unwrap();
+        let rolled = rolling.rolled_files().unwrap();
+        assert_eq!(rolled.len(), 2);
+        let day = now.format("%Y-%m-%d").to_string();
+        assert!(rolled.iter().any(|p| p.to_str().unwrap().ends_with(&day)));
+
+        // 只保留 max_files 个
+        for _ in 0..3 {
+            rolling
+                .write_at(&[b'x'; 40], now + Duration::days(1))
+                .unwrap();
+        }
+        assert_eq!(rolling.rolled_files().unwrap().len(), 2);
+        assert_eq!(fs::read(&path).unwrap(), vec![b'x'; 40]);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use axum::http::header;
+use axum::routing::get;
+use axum::Router;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref REGISTRY: Mutex<BTreeMap<&'static str, Family>> = Default::default();
+}
+
+// 单位是秒
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram(&'static [f64]),
+}
+
+enum Series {
+    Value(f64),
+    Histogram {
+        counts: Vec<u64>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+struct Family {
+    help: &'static str,
+    kind: Kind,
+    // key 是渲染好的 label，例如 method="GET",route="/pay"
+    series: BTreeMap<String, Series>,
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn update(
+    name: &'static str,
+    help: &'static str,
+    kind: Kind,
+    labels: &[(&str, &str)],
+    f: impl FnOnce(&mut Series),
+) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let family = registry.entry(name).or_insert_with(|| Family {
+        help,
+        kind,
+        series: BTreeMap::new(),
+    });
+    let series = family
+        .series
+        .entry(render_labels(labels))
+        .or_insert_with(|| match kind {
+            Kind::Histogram(buckets) => Series::Histogram {
+                counts: vec![0; buckets.len()],
+                sum: 0.0,
+                count: 0,
+            },
+            _ => Series::Value(0.0),
+        });
+    f(series)
+}
+
+// 名称按 prometheus 的习惯，counter 以 _total 结尾，时间以 _seconds 结尾
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self { name, help }
+    }
+
+    pub fn inc(&self, labels: &[(&str, &str)]) {
+        self.add(labels, 1.0)
+    }
+
+    pub fn add(&self, labels: &[(&str, &str)], value: f64) {
+        update(self.name, self.help, Kind::Counter, labels, |series| {
+            if let Series::Value(current) = series {
+                *current += value;
+            }
+        })
+    }
+}
+
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self { name, help }
+    }
+
+    pub fn set(&self, labels: &[(&str, &str)], value: f64) {
+        update(self.name, self.help, Kind::Gauge, labels, |series| {
+            *series = Series::Value(value)
+        })
+    }
+
+    pub fn add(&self, labels: &[(&str, &str)], value: f64) {
+        update(self.name, self.help, Kind::Gauge, labels, |series| {
+            if let Series::Value(current) = series {
+                *current += value;
+            }
+        })
+    }
+}
+
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    buckets: &'static [f64],
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str, help: &'static str, buckets: &'static [f64]) -> Self {
+        Self {
+            name,
+            help,
+            buckets,
+        }
+    }
+
+    pub fn observe(&self, labels: &[(&str, &str)], value: f64) {
+        let buckets = self.buckets;
+        update(
+            self.name,
+            self.help,
+            Kind::Histogram(buckets),
+            labels,
+            |series| {
+                if let Series::Histogram { counts, sum, count } = series {
+                    for (bucket, le) in counts.iter_mut().zip(buckets) {
+                        if value <= *le {
+                            *bucket += 1;
+                        }
+                    }
+                    *sum += value;
+                    *count += 1;
+                }
+            },
+        )
+    }
+}
+
+fn with_le(labels: &str, le: &str) -> String {
+    match labels.is_empty() {
+        true => format!("{{le=\"{}\"}}", le),
+        false => format!("{{{},le=\"{}\"}}", labels, le),
+    }
+}
+
+fn braces(labels: &str) -> String {
+    match labels.is_empty() {
+        true => String::new(),
+        false => format!("{{{}}}", labels),
+    }
+}
+
+// prometheus 的文本格式
+pub fn render() -> String {
+    let registry = REGISTRY.lock().unwrap();
+    let mut out = String::new();
+    for (name, family) in registry.iter() {
+        let kind = match family.kind {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram(_) => "histogram",
+        };
+        let _ = writeln!(out, "# HELP {} {}", name, family.help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for (labels, series) in &family.series {
+            match (series, family.kind) {
+                (Series::Value(value), _) => {
+                    let _ = writeln!(out, "{}{} {}", name, braces(labels), value);
+                }
+                (Series::Histogram { counts, sum, count }, Kind::Histogram(buckets)) => {
+                    for (le, bucket) in buckets.iter().zip(counts) {
+                        let le = le.to_string();
+                        let _ = writeln!(out, "{}_bucket{} {}", name, with_le(labels, &le), bucket);
+                    }
+                    let _ = writeln!(out, "{}_bucket{} {}", name, with_le(labels, "+Inf"), count);
+                    let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), sum);
+                    let _ = writeln!(out, "{}_count{} {}", name, braces(labels), count);
+                }
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+// 和 /healthz 一样不需要登录，部署时不要对外暴露
+pub fn router() -> Router {
+    Router::new().route(
+        "/metrics",
+        get(|| async {
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                render(),
+            )
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_text() {
+        static REQUESTS: Counter = Counter::new("test_requests_total", "requests");
+        static IN_FLIGHT: Gauge = Gauge::new("test_in_flight", "in flight");
+        static LATENCY: Histogram = Histogram::new("test_seconds", "latency", &[0.1, 1.0]);
+
+        REQUESTS.inc(&[("route", "/pay"), ("status", "200")]);
+        REQUESTS.add(&[("route", "/pay"), ("status", "200")], 2.0);
+        REQUESTS.inc(&[("route", "a\"b")]);
+        IN_FLIGHT.add(&[], 2.0);
+        IN_FLIGHT.add(&[], -1.0);
+        LATENCY.observe(&[("rpc", "operate")], 0.05);
+        LATENCY.observe(&[("rpc", "operate")], 0.5);
+        LATENCY.observe(&[("rpc", "operate")], 3.0);
+
+        let text = render();
+        for line in [
+            "# TYPE test_requests_total counter",
+            "test_requests_total{route=\"/pay\",status=\"200\"} 3",
+            "test_requests_total{route=\"a\\\"b\"} 1",
+            "# TYPE test_in_flight gauge",
+            "test_in_flight 1",
+            "# TYPE test_seconds histogram",
+            "test_seconds_bucket{rpc=\"operate\",le=\"0.1\"} 1",
+            "test_seconds_bucket{rpc=\"operate\",le=\"1\"} 2",
+            "test_seconds_bucket{rpc=\"operate\",le=\"+Inf\"} 3",
+            "test_seconds_sum{rpc=\"operate\"} 3.55",
+            "test_seconds_count{rpc=\"operate\"} 3",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::anyhow;
+use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize};
+use tokio::sync::mpsc::Sender;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+use tower::discover::Change;
+
+use crate::metrics::{Counter, Histogram, LATENCY_BUCKETS};
+use crate::pb::tls;
+use crate::trace::{self, SpanData, SpanKind, TraceContext};
+
+static GRPC_CLIENT_HANDLED: Counter = Counter::new(
+    "grpc_client_handled_total",
+    "gRPC calls to other services by service, rpc and code",
+);
+// 包括重试和等待的时间
+static GRPC_CLIENT_DURATION: Histogram = Histogram::new(
+    "grpc_client_handling_seconds",
+    "gRPC client latency by service and rpc",
+    LATENCY_BUCKETS,
+);
+static GRPC_CLIENT_RETRIES: Counter = Counter::new(
+    "grpc_client_retries_total",
+    "gRPC client retries by service and rpc",
+);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+    // 多个地址时在其中负载均衡，也可以是逗号分隔的字符串
+    #[serde(deserialize_with = "endpoints")]
+    pub endpoints: Vec<String>,
+    pub connect_timeout_ms: u64,
+    // 每次请求的默认 deadline，包括等待可用连接的时间
+    pub timeout_ms: u64,
+    // 单独配置某个 rpc 的 deadline，例如 operate = 5000
+    pub deadlines: HashMap<String, u64>,
+    // 连接失败后重试的间隔，每次翻倍直到 backoff_max_ms
+    pub backoff_ms: u64,
+    pub backoff_max_ms: u64,
+    // 只有幂等的 rpc 会重试，间隔带随机抖动
+    pub retries: u32,
+    pub retry_backoff_ms: u64,
+    // 连续失败 failure_threshold 次后熔断 open_ms，之后放一个请求试探
+    pub failure_threshold: u32,
+    pub open_ms: u64,
+}
+
+fn endpoints<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Endpoints {
+        Urls(String),
+        List(Vec<String>),
+    }
+    Ok(match Endpoints::deserialize(deserializer)? {
+        Endpoints::Urls(urls) => ChannelConfig::from_urls(&urls).endpoints,
+        Endpoints::List(list) => list,
+    })
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![],
+            connect_timeout_ms: 1000,
+            timeout_ms: 3000,
+            deadlines: HashMap::new(),
+            backoff_ms: 100,
+            backoff_max_ms: 10_000,
+            retries: 2,
+            retry_backoff_ms: 50,
+            failure_threshold: 5,
+            open_ms: 5000,
+        }
+    }
+}
+
+impl ChannelConfig {
+    // 逗号分隔多个地址，空字符串表示不调用该服务
+    pub fn from_urls(urls: &str) -> Self {
+        Self {
+            endpoints: urls
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(String::from)
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn exponential(&self, base_ms: u64, attempt: u32) -> Duration {
+        let ms = base_ms
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.backoff_max_ms);
+        Duration::from_millis(ms)
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.exponential(self.backoff_ms, attempt)
+    }
+
+    // full jitter，避免所有调用方同时重试
+    fn retry_backoff(&self, attempt: u32) -> Duration {
+        let max = self.exponential(self.retry_backoff_ms, attempt);
+        rand::thread_rng().gen_range(Duration::ZERO..=max)
+    }
+
+    pub fn deadline(&self, rpc: &str) -> Duration {
+        Duration::from_millis(*self.deadlines.get(rpc).unwrap_or(&self.timeout_ms))
+    }
+
+    // 超时由 call 按 rpc 控制
+    fn endpoint(&self, url: &str) -> anyhow::Result<Endpoint> {
+        Ok(tls::endpoint(url.to_string())?
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
+            .tcp_keepalive(Some(Duration::from_secs(60))))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    Timeout { rpc: String, deadline: Duration },
+    // 熔断期间直接失败，不再请求下游
+    CircuitOpen { service: String },
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::Timeout { rpc, deadline } => {
+                write!(f, "grpc {} timeout after {:?}", rpc, deadline)
+            }
+            CallError::CircuitOpen { service } => {
+                write!(f, "{} service unavailable, circuit open", service)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl CallError {
+    // 对应的 http 状态码，也用作 Response 的 code
+    pub fn code(&self) -> u16 {
+        match self {
+            CallError::Timeout { .. } => 504,
+            CallError::CircuitOpen { .. } => 503,
+        }
+    }
+}
+
+// 下游不健康时才计入熔断，业务错误说明下游正常
+fn unhealthy(e: &anyhow::Error) -> bool {
+    if let Some(CallError::Timeout { .. }) = e.downcast_ref::<CallError>() {
+        return true;
+    }
+    matches!(
+        e.downcast_ref::<Status>().map(Status::code),
+        Some(Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled)
+    )
+}
+
+// 用于 grpc_client_handled_total 的 code
+fn error_code(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<CallError>() {
+        Some(CallError::Timeout { .. }) => format!("{:?}", Code::DeadlineExceeded),
+        Some(CallError::CircuitOpen { .. }) => "CircuitOpen".to_string(),
+        None => e
+            .downcast_ref::<Status>()
+            .map_or("Unknown".to_string(), |status| {
+                format!("{:?}", status.code())
+            }),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+    // 半开时正在试探的请求，超过 open_ms 没有结果则允许再试探
+    probing: Option<Instant>,
+}
+
+impl Breaker {
+    fn acquire(&mut self, now: Instant, open: Duration) -> bool {
+        match self.open_until {
+            None => true,
+            Some(until) if now < until => false,
+            Some(_) => match self.probing {
+                Some(start) if now < start + open => false,
+                _ => {
+                    self.probing = Some(now);
+                    true
+                }
+            },
+        }
+    }
+
+    fn record(&mut self, healthy: bool, now: Instant, config: &ChannelConfig) {
+        if healthy {
+            *self = Self::default();
+            return;
+        }
+        self.probing = None;
+        self.failures += 1;
+        if self.failures >= config.failure_threshold || self.open_until.is_some() {
+            self.open_until = Some(now + Duration::from_millis(config.open_ms));
+        }
+    }
+}
+
+// channel 可以直接 clone，所有 clone 共用底层连接和熔断状态
+#[derive(Debug, Clone)]
+pub struct ServiceChannel {
+    name: String,
+    channel: Channel,
+    config: Arc<ChannelConfig>,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl ServiceChannel {
+    // 需要在 tokio runtime 中调用，不会等待连接建立
+    pub fn lazy(name: &str, config: &ChannelConfig) -> anyhow::Result<Self> {
+        if config.endpoints.is_empty() {
+            return Err(anyhow!("no grpc endpoint"));
+        }
+        let endpoints = config
+            .endpoints
+            .iter()
+            .map(|url| config.endpoint(url))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let (channel, tx) = Channel::balance_channel(endpoints.len());
+        for (key, endpoint) in endpoints.into_iter().enumerate() {
+            tokio::spawn(discover(key, endpoint, config.clone(), tx.clone()));
+        }
+        Ok(Self::from_channel(name, channel, config.clone()))
+    }
+
+    // 测试时可以注入已经建好的 channel
+    pub fn from_channel(name: &str, channel: Channel, config: ChannelConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            channel,
+            config: Arc::new(config),
+            breaker: Default::default(),
+        }
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel.clone()
+    }
+
+    // 每次尝试都会调用 f 重新构造请求
+    pub async fn call<T, F, Fut>(&self, rpc: &str, idempotent: bool, f: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<tonic::Response<T>>>,
+    {
+        // 重试都在同一个 client span 中，inject 时带上的是这个 span
+        let ctx = trace::current()
+            .map(|ctx| ctx.child())
+            .unwrap_or_else(|| TraceContext::root(None));
+        let (start, started_at) = (Instant::now(), SystemTime::now());
+        let result = trace::scope(ctx.clone(), self.retry(rpc, idempotent, f)).await;
+        let code = match &result {
+            Ok(_) => "Ok".to_string(),
+            Err(e) => error_code(e),
+        };
+        let labels = [("service", self.name.as_str()), ("rpc", rpc)];
+        GRPC_CLIENT_HANDLED.inc(&[labels[0], labels[1], ("code", &code)]);
+        GRPC_CLIENT_DURATION.observe(&labels, start.elapsed().as_secs_f64());
+        let name = format!("{}/{}", self.name, rpc);
+        let mut span = SpanData::new(&ctx, name, SpanKind::Client, started_at);
+        span.error = result.is_err();
+        span.attributes.push(("rpc.grpc.status_code", code));
+        trace::export(span);
+        result
+    }
+
+    async fn retry<T, F, Fut>(&self, rpc: &str, idempotent: bool, mut f: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<tonic::Response<T>>>,
+    {
+        let deadline = self.config.deadline(rpc);
+        let retries = if idempotent { self.config.retries } else { 0 };
+        let open = Duration::from_millis(self.config.open_ms);
+        let mut attempt = 0;
+        loop {
+            if !self.breaker.lock().unwrap().acquire(Instant::now(), open) {
+                return Err(CallError::CircuitOpen {
+                    service: self.name.clone(),
+                }
+                .into());
+            }
+            let result = match tokio::time::timeout(deadline, f()).await {
+                Ok(result) => result,
+                Err(_) => Err(CallError::Timeout {
+                    rpc: rpc.to_string(),
+                    deadline,
+                }
+                .into()),
+            };
+            let healthy = !matches!(&result, Err(e) if unhealthy(e));
+            self.breaker
+                .lock()
+                .unwrap()
+                .record(healthy, Instant::now(), &self.config);
+            match result {
+                Ok(resp) => return Ok(resp.into_inner()),
+                Err(e) if healthy || attempt >= retries => return Err(e),
+                Err(e) => {
+                    let wait = self.config.retry_backoff(attempt);
+                    tracing::warn!("grpc {} failed,{}, retry in {:?}", rpc, e, wait);
+                    GRPC_CLIENT_RETRIES.inc(&[("service", &self.name), ("rpc", rpc)]);
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+// 连上之后才加入负载均衡，之后断线由 channel 自己重连
+async fn discover(
+    key: usize,
+    endpoint: Endpoint,
+    config: ChannelConfig,
+    tx: Sender<Change<usize, Endpoint>>,
+) {
+    let mut attempt = 0;
+    loop {
+        match endpoint.connect().await {
+            Ok(_) => {
+                let _ = tx.send(Change::Insert(key, endpoint)).await;
+                return;
+            }
+            Err(e) => {
+                let wait = config.backoff(attempt);
+                tracing::warn!(
+                    "connect {} failed,{}, retry in {:?}",
+                    endpoint.uri(),
+                    e,
+                    wait
+                );
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::validate::validate_client::ValidateClient;
+    use crate::pb::validate::TokenRequest;
+
+    #[test]
+    fn config() {
+        let mut config = ChannelConfig::from_urls("http://127.0.0.1:8090, http://127.0.0.1:8091,");
+        assert_eq!(
+            config.endpoints,
+            vec!["http://127.0.0.1:8090", "http://127.0.0.1:8091"]
+        );
+        assert!(ChannelConfig::from_urls("").endpoints.is_empty());
+
+        assert_eq!(config.backoff(0), Duration::from_millis(100));
+        assert_eq!(config.backoff(3), Duration::from_millis(800));
+        assert_eq!(config.backoff(40), Duration::from_millis(10_000));
+        assert!(config.retry_backoff(2) <= Duration::from_millis(200));
+
+        config.deadlines.insert("operate".to_string(), 5000);
+        assert_eq!(config.deadline("operate"), Duration::from_millis(5000));
+        assert_eq!(config.deadline("validate"), Duration::from_millis(3000));
+    }
+
+    fn test_channel(config: ChannelConfig) -> ServiceChannel {
+        let channel = Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+        ServiceChannel::from_channel("test", channel, config)
+    }
+
+    async fn fail(channel: &ServiceChannel, idempotent: bool, code: Code) -> (u32, anyhow::Error) {
+        let mut attempts = 0;
+        let err = channel
+            .call("rpc", idempotent, || {
+                attempts += 1;
+                async move { Err::<tonic::Response<()>, _>(Status::new(code, "").into()) }
+            })
+            .await
+            .unwrap_err();
+        (attempts, err)
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let channel = test_channel(ChannelConfig {
+            retry_backoff_ms: 1,
+            failure_threshold: 100,
+            ..Default::default()
+        });
+        assert_eq!(fail(&channel, true, Code::Unavailable).await.0, 3);
+        // 非幂等和业务错误不重试
+        assert_eq!(fail(&channel, false, Code::Unavailable).await.0, 1);
+        assert_eq!(fail(&channel, true, Code::InvalidArgument).await.0, 1);
+    }
+
+    #[tokio::test]
+    async fn circuit() {
+        let channel = test_channel(ChannelConfig {
+            retries: 0,
+            failure_threshold: 2,
+            open_ms: 50,
+            ..Default::default()
+        });
+        fail(&channel, true, Code::Unavailable).await;
+        fail(&channel, true, Code::Unavailable).await;
+        let (attempts, err) = fail(&channel, true, Code::Unavailable).await;
+        assert_eq!(attempts, 0);
+        assert_eq!(
+            err.downcast_ref::<CallError>(),
+            Some(&CallError::CircuitOpen {
+                service: "test".to_string()
+            })
+        );
+
+        // 熔断结束后试探成功则恢复
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let ok = channel
+            .call("rpc", true, || async { Ok(tonic::Response::new(1)) })
+            .await
+            .unwrap();
+        assert_eq!(ok, 1);
+        assert_eq!(fail(&channel, true, Code::Unavailable).await.0, 1);
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        let channel = Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+        let channel = ServiceChannel::from_channel(
+            "metrics",
+            channel,
+            ChannelConfig {
+                retry_backoff_ms: 1,
+                ..Default::default()
+            },
+        );
+        fail(&channel, true, Code::Unavailable).await;
+        fail(&channel, true, Code::NotFound).await;
+        let text = crate::metrics::render();
+        for line in [
+            "grpc_client_handled_total{service=\"metrics\",rpc=\"rpc\",code=\"Unavailable\"} 1",
+            "grpc_client_handled_total{service=\"metrics\",rpc=\"rpc\",code=\"NotFound\"} 1",
+            "grpc_client_retries_total{service=\"metrics\",rpc=\"rpc\"} 2",
+            "grpc_client_handling_seconds_count{service=\"metrics\",rpc=\"rpc\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable() {
+        let config = ChannelConfig {
+            timeout_ms: 100,
+            retries: 0,
+            ..ChannelConfig::from_urls("http://127.0.0.1:1")
+        };
+        let channel = ServiceChannel::lazy("validate", &config).unwrap();
+        let pb = ValidateClient::new(channel.channel());
+        let err = channel
+            .call("validate_token", true, || {
+                let mut pb = pb.clone();
+                async move { Ok(pb.validate_token(TokenRequest::default()).await?) }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<CallError>(),
+            Some(&CallError::Timeout {
+                rpc: "validate_token".to_string(),
+                deadline: Duration::from_millis(100)
+            })
+        );
+    }
+}
@@ -1,92 +1,316 @@
 use super::validate::validate_client::ValidateClient;
 use crate::axum::auth::{Home, UserToken, Wallet};
+use crate::config::PeersConfig;
+use crate::pb::channel::{ChannelConfig, ServiceChannel};
 use crate::pb::home::home_client::HomeClient;
 use crate::pb::home::{GetAllHomeRequest, HomeId};
+use crate::pb::service_auth::SERVICE_TOKEN_KEY;
 use crate::pb::validate::{
-    AddUrlAuthRequest, AuthRequest, GetAllAuthRequest, TokenRequest, ValidateRequest,
+    AddUrlAuthRequest, AuthRequest, ClientTokenRequest, GetAllAuthRequest, RevokedRequest,
+    TokenRequest, ValidateRequest,
 };
 use crate::pb::wallet::wallet_client::WalletClient;
 use crate::pb::wallet::{operate_request, OperateRequest};
-use tonic::transport::Channel;
-use tonic::Request;
+use crate::trace;
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tonic::{Request, Status};
 
-static mut V_CLIENT: String = String::new();
-static mut H_CLIENT: String = String::new();
-static mut W_CLIENT: String = String::new();
+lazy_static! {
+    static ref CLIENTS: RwLock<ServiceClients> = RwLock::new(ServiceClients::default());
+    // (client_id, client_secret)
+    static ref CLIENT_CREDENTIAL: RwLock<Option<(String, String)>> = RwLock::new(None);
+    // (token, 过期时间)
+    static ref SERVICE_TOKEN: tokio::sync::Mutex<Option<(String, Instant)>> =
+        tokio::sync::Mutex::new(None);
+}
 
 type Result<T> = anyhow::Result<T>;
 
-async fn validate_client() -> ValidateClient<Channel> {
-    ValidateClient::connect(validate).await.ok().unwrap()
+// 没有配置的服务为 None，调用时报错
+#[derive(Debug, Clone, Default)]
+pub struct ServiceClients {
+    pub validate: Option<ServiceChannel>,
+    pub home: Option<ServiceChannel>,
+    pub wallet: Option<ServiceChannel>,
+}
+
+impl ServiceClients {
+    // 需要在 tokio runtime 中调用
+    pub fn from_config(
+        validate: &ChannelConfig,
+        home: &ChannelConfig,
+        wallet: &ChannelConfig,
+    ) -> Result<Self> {
+        let lazy = |name: &str, config: &ChannelConfig| {
+            if config.endpoints.is_empty() {
+                return Ok(None);
+            }
+            ServiceChannel::lazy(name, config).map(Some)
+        };
+        Ok(Self {
+            validate: lazy("validate", validate)?,
+            home: lazy("home", home)?,
+            wallet: lazy("wallet", wallet)?,
+        })
+    }
+
+    fn validate(&self) -> Result<&ServiceChannel> {
+        self.validate
+            .as_ref()
+            .ok_or(anyhow!("validate service not configured"))
+    }
+
+    fn home(&self) -> Result<&ServiceChannel> {
+        self.home
+            .as_ref()
+            .ok_or(anyhow!("home service not configured"))
+    }
+
+    fn wallet(&self) -> Result<&ServiceChannel> {
+        self.wallet
+            .as_ref()
+            .ok_or(anyhow!("wallet service not configured"))
+    }
+}
+
+// 测试时可以替换成自己的 channel
+pub fn set_clients(clients: ServiceClients) {
+    *CLIENTS.write().unwrap() = clients;
 }
 
-async fn home_client() -> HomeClient<Channel> {
-    HomeClient::connect(home).await.ok().unwrap()
+pub fn clients() -> ServiceClients {
+    CLIENTS.read().unwrap().clone()
 }
 
-async fn wallet_client() -> WalletClient<Channel> {
-    WalletClient::connect(wallet).await.ok().unwrap()
+// 使用 tls 时需要先调用 tls::init
+pub fn init(peers: &PeersConfig) {
+    let clients = ServiceClients::from_config(&peers.validate, &peers.home, &peers.wallet)
+        .expect("init grpc clients failed");
+    set_clients(clients)
 }
 
-pub async fn init(validate: String, home: String, wallet: String) {
-    unsafe {
-        V_CLIENT = validate;
-        H_CLIENT = home;
-        W_CLIENT = wallet;
+pub fn set_client_credentials(client_id: String, client_secret: String) {
+    *CLIENT_CREDENTIAL.write().unwrap() = Some((client_id, client_secret));
+}
+
+pub async fn client_token(client_id: String, client_secret: String) -> Result<(String, i64)> {
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = ClientTokenRequest {
+        client_id,
+        client_secret,
+    };
+    let resp = channel
+        .call("client_token", true, || {
+            let (mut pb, message) = (pb.clone(), message.clone());
+            async move {
+                let mut request = Request::new(message);
+                trace::inject(request.metadata_mut());
+                Ok(pb.client_token(request).await?)
+            }
+        })
+        .await?;
+    Ok((resp.token, resp.expires_in))
+}
+
+// 没有配置 client 时不带 token，过期前一分钟重新获取
+async fn service_token() -> Result<Option<String>> {
+    let credential = CLIENT_CREDENTIAL.read().unwrap().clone();
+    let (client_id, client_secret) = match credential {
+        None => return Ok(None),
+        Some(credential) => credential,
+    };
+    let mut cached = SERVICE_TOKEN.lock().await;
+    if let Some((token, expire_at)) = cached.as_ref() {
+        if Instant::now() + Duration::from_secs(60) < *expire_at {
+            return Ok(Some(token.clone()));
+        }
+    }
+    let (token, expires_in) = client_token(client_id, client_secret).await?;
+    let expire_at = Instant::now() + Duration::from_secs(expires_in.max(0) as u64);
+    *cached = Some((token.clone(), expire_at));
+    Ok(Some(token))
+}
+
+async fn with_service_token<T>(message: T) -> Result<Request<T>> {
+    let mut request = Request::new(message);
+    trace::inject(request.metadata_mut());
+    if let Some(token) = service_token().await? {
+        request
+            .metadata_mut()
+            .insert(SERVICE_TOKEN_KEY, token.parse()?);
     }
+    Ok(request)
+}
+
+// 重试时重新构造请求，带上 service token
+async fn call<C, M, T, Fut>(
+    channel: &ServiceChannel,
+    rpc: &str,
+    idempotent: bool,
+    client: C,
+    message: M,
+    f: impl Fn(C, Request<M>) -> Fut,
+) -> Result<T>
+where
+    C: Clone,
+    M: Clone,
+    Fut: Future<Output = std::result::Result<tonic::Response<T>, Status>>,
+{
+    let (client, message, f) = (&client, &message, &f);
+    channel
+        .call(rpc, idempotent, move || async move {
+            let request = with_service_token(message.clone()).await?;
+            Ok(f(client.clone(), request).await?)
+        })
+        .await
 }
 
 pub async fn validate_token(token: String) -> Result<UserToken> {
-    let mut pb = validate_client();
-    Ok(pb
-        .validate_token(Request::new(TokenRequest { token }))
-        .await?
-        .into_inner()
-        .into())
-}
-
-pub async fn validate_auth(user_id: u64, url: String) -> Result<bool> {
-    let mut pb = validate_client();
-    Ok(pb
-        .validate_auth(Request::new(AuthRequest { user_id, url }))
-        .await?
-        .into_inner()
-        .ok)
-}
-
-pub async fn validate(token: String, url: String) -> Result<(UserToken, bool)> {
-    let mut pb = validate_client();
-    let resp = pb
-        .validate(Request::new(ValidateRequest { token, url }))
-        .await?
-        .into_inner();
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = TokenRequest { token };
+    let resp = call(
+        channel,
+        "validate_token",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.validate_token(r).await },
+    )
+    .await?;
+    Ok(resp.into())
+}
+
+pub async fn check_revoked(jti: String, sid: String, user_id: u64, issued_at: i64) -> Result<bool> {
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = RevokedRequest {
+        jti,
+        sid,
+        user_id,
+        issued_at,
+    };
+    let resp = call(
+        channel,
+        "check_revoked",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.check_revoked(r).await },
+    )
+    .await?;
+    Ok(resp.revoked)
+}
+
+pub async fn validate_auth(user_id: u64, method: String, url: String) -> Result<bool> {
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = AuthRequest {
+        user_id,
+        url,
+        method,
+    };
+    let resp = call(
+        channel,
+        "validate_auth",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.validate_auth(r).await },
+    )
+    .await?;
+    Ok(resp.ok)
+}
+
+pub async fn validate(token: String, method: String, url: String) -> Result<(UserToken, bool)> {
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = ValidateRequest { token, url, method };
+    let resp = call(
+        channel,
+        "validate",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.validate(r).await },
+    )
+    .await?;
     Ok((resp.user.unwrap().into(), resp.auth.unwrap().ok))
 }
 
-pub async fn add_url_auth(url: String, auth: String) -> Result<bool> {
-    let mut pb = validate_client();
-    Ok(pb
-        .add_url_auth(Request::new(AddUrlAuthRequest { url, auth }))
-        .await?
-        .into_inner()
-        .ok)
+// route 可以带上 method，例如 "POST /comment_to"，不带时匹配所有方法
+pub fn split_route(route: &str) -> (&str, &str) {
+    match route.trim().split_once(' ') {
+        Some((method, url)) => (method, url.trim()),
+        None => ("", route.trim()),
+    }
+}
+
+pub async fn add_url_auth(route: String, auth: String) -> Result<bool> {
+    let (method, url) = split_route(&route);
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = AddUrlAuthRequest {
+        url: url.to_string(),
+        auth,
+        method: method.to_string(),
+    };
+    // 覆盖写入，可以重试
+    let resp = call(
+        channel,
+        "add_url_auth",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.add_url_auth(r).await },
+    )
+    .await?;
+    Ok(resp.ok)
 }
 
 pub async fn get_all_auth(user_id: u64) -> Result<Vec<String>> {
-    let mut pb = validate_client();
-    Ok(pb
-        .get_all_auth(Request::new(GetAllAuthRequest { user_id }))
-        .await?
-        .into_inner()
-        .auth_infos)
+    let clients = clients();
+    let channel = clients.validate()?;
+    let pb = ValidateClient::new(channel.channel());
+    let message = GetAllAuthRequest { user_id };
+    let resp = call(
+        channel,
+        "get_all_auth",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.get_all_auth(r).await },
+    )
+    .await?;
+    Ok(resp.auth_infos)
 }
 
 pub async fn get_all_home(user_id: u64) -> Result<Vec<Home>> {
-    let mut pb = home_client();
-    Ok(pb
-        .get_all_home(Request::new(GetAllHomeRequest { user_id }))
-        .await?
-        .into_inner()
+    let clients = clients();
+    let channel = clients.home()?;
+    let pb = HomeClient::new(channel.channel());
+    let message = GetAllHomeRequest { user_id };
+    let resp = call(
+        channel,
+        "get_all_home",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.get_all_home(r).await },
+    )
+    .await?;
+    Ok(resp
         .home_addresses
         .into_iter()
         .map(|home| Home::from(home))
@@ -94,12 +318,20 @@ pub async fn get_all_home(user_id: u64) -> Result<Vec<Home>> {
 }
 
 pub async fn get_home_by_id(home_id: u64) -> Result<Home> {
-    let mut pb = home_client();
-    Ok(pb
-        .get_home_by_id(Request::new(HomeId { home_id }))
-        .await?
-        .into_inner()
-        .into())
+    let clients = clients();
+    let channel = clients.home()?;
+    let pb = HomeClient::new(channel.channel());
+    let message = HomeId { home_id };
+    let resp = call(
+        channel,
+        "get_home_by_id",
+        true,
+        pb,
+        message,
+        |mut pb, r| async move { pb.get_home_by_id(r).await },
+    )
+    .await?;
+    Ok(resp.into())
 }
 
 pub enum WalletIndex {
@@ -122,11 +354,23 @@ impl Into<OperateRequest> for WalletIndex {
 }
 
 pub async fn operate_wallet(wallet: WalletIndex, num: i64, force: bool) -> Result<Wallet> {
-    let mut pb = wallet_client();
     let mut request: OperateRequest = wallet.into();
     request.num = num;
     request.force = force;
-    Ok(pb.operate(Request::new(request)).await?.into_inner().into())
+    let clients = clients();
+    let channel = clients.wallet()?;
+    let pb = WalletClient::new(channel.channel());
+    // 扣款不是幂等的，失败后不重试
+    let resp = call(
+        channel,
+        "operate",
+        false,
+        pb,
+        request,
+        |mut pb, r| async move { pb.operate(r).await },
+    )
+    .await?;
+    Ok(resp.into())
 }
 
 #[cfg(test)]
@@ -137,12 +381,11 @@ mod test {
     #[test]
     fn validate_token_info() {
         tokio_test::block_on(async {
-            init(
-                "http://127.0.0.1:8089".to_string(),
-                "http://127.0.0.1:8090".to_string(),
-                "".to_string(),
-            )
-            .await;
+            init(&PeersConfig::from_urls(
+                "http://127.0.0.1:8089",
+                "http://127.0.0.1:8090",
+                "",
+            ));
             let token = "eyJ1c2VyX25hbWUiOiIzMzMiLCJ1c2VyX2lkIjoxLCJkYXRhIjp7fSwidGltZV9vdXQiOjE2NzY3ODU1OTd9LjY1ZjVmYTBkMzk0N2YxMzZmNzgwMGM3YmE3YTA4YjBiZWY2YmM3MjYwNGVlMTBkNzE5MzI4MWUwODc2NjAyMzQ=".to_string();
 
             let user = join!(
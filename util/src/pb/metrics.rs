@@ -0,0 +1,110 @@
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use hyper::{Request, Response};
+use tonic::Code;
+use tower::{Layer, Service};
+
+use crate::metrics::{Counter, Histogram, LATENCY_BUCKETS};
+
+static GRPC_SERVER_HANDLED: Counter = Counter::new(
+    "grpc_server_handled_total",
+    "gRPC calls handled by method and code",
+);
+static GRPC_SERVER_DURATION: Histogram = Histogram::new(
+    "grpc_server_handling_seconds",
+    "gRPC server latency by method",
+    LATENCY_BUCKETS,
+);
+
+// 放在 ServiceAuthLayer 外面，没有通过校验的请求也会统计
+#[derive(Clone, Default)]
+pub struct GrpcMetricsLayer;
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetrics { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct GrpcMetrics<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for GrpcMetrics<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let method = request.uri().path().to_string();
+        let start = Instant::now();
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let result = future.await;
+            let code = match &result {
+                Ok(response) => code(response),
+                Err(_) => Code::Unknown,
+            };
+            let code = format!("{:?}", code);
+            GRPC_SERVER_HANDLED.inc(&[("method", &method), ("code", &code)]);
+            GRPC_SERVER_DURATION.observe(&[("method", &method)], start.elapsed().as_secs_f64());
+            result
+        })
+    }
+}
+
+// 出错时 tonic 只返回 header，grpc-status 在 header 中，成功时在 trailer 中
+fn code<B>(response: &Response<B>) -> Code {
+    response
+        .headers()
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok())
+        .and_then(|status| status.parse::<i32>().ok())
+        .map(Code::from_i32)
+        .unwrap_or(Code::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::render;
+    use hyper::Body;
+    use tonic::Status;
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn server() {
+        let service = GrpcMetricsLayer.layer(service_fn(|request: Request<Body>| async move {
+            match request.uri().path() {
+                "/Home.Home/get_all_home" => {
+                    Ok::<_, Status>(Response::new(tonic::body::empty_body()))
+                }
+                _ => Ok(Status::permission_denied("missing scope").to_http()),
+            }
+        }));
+        for path in ["/Home.Home/get_all_home", "/Wallet.Wallet/operate"] {
+            let request = Request::post(path).body(Body::empty()).unwrap();
+            service.clone().oneshot(request).await.unwrap();
+        }
+        let text = render();
+        assert!(text.contains(
+            "grpc_server_handled_total{method=\"/Home.Home/get_all_home\",code=\"Ok\"} 1"
+        ));
+        assert!(text.contains(
+            "grpc_server_handled_total{method=\"/Wallet.Wallet/operate\",code=\"PermissionDenied\"} 1"
+        ));
+    }
+}
@@ -1,5 +1,14 @@
+use crate::axum::auth::UserToken;
+use tonic::{Request, Status};
+
+pub mod channel;
 pub mod client;
+#[path = "grpc.health.v1.rs"]
+pub mod health;
 pub mod home;
+pub mod metrics;
+pub mod service_auth;
+pub mod tls;
 pub mod validate;
 pub mod wallet;
 
@@ -17,3 +26,20 @@ pub async fn init_url_auth(url_auths: &[(&str, &str)]) {
         check_url_auth(url_auth.0, url_auth.1).await
     }
 }
+
+// grpc 服务端校验调用方 token 中的 scope，token 由 ServiceAuthLayer 校验后放入 extensions
+#[allow(clippy::result_large_err)]
+pub fn require_scope<T>(request: &Request<T>, scope: &str) -> Result<UserToken, Status> {
+    let user = request
+        .extensions()
+        .get::<UserToken>()
+        .cloned()
+        .ok_or(Status::unauthenticated("missing service token"))?;
+    if !user.has_scope(scope) {
+        return Err(Status::permission_denied(format!(
+            "missing scope {}",
+            scope
+        )));
+    }
+    Ok(user)
+}
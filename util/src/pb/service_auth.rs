@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use hyper::{Body, Request, Response};
+use tonic::body::BoxBody;
+use tonic::Status;
+use tower::{Layer, Service};
+
+use crate::axum::auth::UserToken;
+use crate::pb::client::validate_token;
+
+// 服务之间调用的 token 放在这个 metadata 中，authorization 留给用户的 token
+pub const SERVICE_TOKEN_KEY: &str = "x-service-token";
+
+const MAX_CACHED: usize = 1024;
+
+const HEALTH_PATHS: &[&str] = &[
+    "/grpc.health.v1.Health/Check",
+    "/grpc.health.v1.Health/Watch",
+];
+
+#[async_trait]
+pub trait TokenVerifier: Send + Sync + 'static {
+    async fn verify(&self, token: &str) -> anyhow::Result<UserToken>;
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// 通过 login 校验，login 会检查 token 是否撤销
+// 校验过的 token 最多缓存 ttl，撤销或泄露的 client token 最多再使用 ttl
+pub struct RemoteVerifier {
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (UserToken, Instant)>>,
+}
+
+impl Default for RemoteVerifier {
+    // 和本地校验的 revocation_ttl_secs 一致
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl RemoteVerifier {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Default::default(),
+        }
+    }
+
+    fn cached(&self, token: &str) -> Option<UserToken> {
+        match self.cache.lock().unwrap().get(token) {
+            Some((user, at)) if at.elapsed() < self.ttl && user.time_out > now() => {
+                Some(user.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn store(&self, token: &str, user: &UserToken) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED {
+            cache.retain(|_, (user, at)| at.elapsed() < self.ttl && user.time_out > now());
+        }
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(token.to_string(), (user.clone(), Instant::now()));
+    }
+}
+
+#[async_trait]
+impl TokenVerifier for RemoteVerifier {
+    async fn verify(&self, token: &str) -> anyhow::Result<UserToken> {
+        if let Some(user) = self.cached(token) {
+            return Ok(user);
+        }
+        let user = validate_token(token.to_string()).await?;
+        self.store(token, &user);
+        Ok(user)
+    }
+}
+
+// 校验每个 rpc 的 service token，通过后放入 request 的 extensions
+#[derive(Clone)]
+pub struct ServiceAuthLayer {
+    verifier: Arc<dyn TokenVerifier>,
+    // 不需要 token 的方法，例如 /Validate.Validate/client_token
+    skip: Arc<HashSet<String>>,
+}
+
+impl ServiceAuthLayer {
+    // 健康检查由 grpc_health_probe 和其他服务的 /readyz 调用，不带 token
+    pub fn new(verifier: impl TokenVerifier) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            skip: Arc::new(HEALTH_PATHS.iter().map(|path| path.to_string()).collect()),
+        }
+    }
+
+    pub fn skip(mut self, path: &str) -> Self {
+        Arc::make_mut(&mut self.skip).insert(path.to_string());
+        self
+    }
+}
+
+impl<S> Layer<S> for ServiceAuthLayer {
+    type Service = ServiceAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ServiceAuth {
+            verifier: self.verifier.clone(),
+            skip: self.skip.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceAuth<S> {
+    verifier: Arc<dyn TokenVerifier>,
+    skip: Arc<HashSet<String>>,
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for ServiceAuth<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // 使用已经 poll_ready 的 inner
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+        let skip = self.skip.contains(request.uri().path());
+        let token = request
+            .headers()
+            .get(SERVICE_TOKEN_KEY)
+            .and_then(|token| token.to_str().ok())
+            .map(|token| token.to_string());
+        Box::pin(async move {
+            if !skip {
+                let user = match token {
+                    None => Err(Status::unauthenticated("missing service token")),
+                    Some(token) => match verifier.verify(&token).await {
+                        // client credential 换取的 token 的 user_id 为 0
+                        Ok(user) if user.user_id == 0 => Ok(user),
+                        Ok(_) => Err(Status::unauthenticated("not a service token")),
+                        Err(e) => Err(Status::unauthenticated(e.to_string())),
+                    },
+                };
+                match user {
+                    Ok(user) => request.extensions_mut().insert(user),
+                    Err(status) => return Ok(status.to_http()),
+                };
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::{service_fn, ServiceExt};
+
+    struct StaticVerifier;
+
+    #[async_trait]
+    impl TokenVerifier for StaticVerifier {
+        async fn verify(&self, token: &str) -> anyhow::Result<UserToken> {
+            match token {
+                "service" => Ok(UserToken {
+                    user_name: "sale".to_string(),
+                    user_id: 0,
+                    data: Default::default(),
+                    time_out: now() + 60,
+                    scopes: vec!["wallet:debit".parse()?],
+                }),
+                "user" => Ok(UserToken {
+                    user_name: "user".to_string(),
+                    user_id: 1,
+                    data: Default::default(),
+                    time_out: now() + 60,
+                    scopes: vec![],
+                }),
+                _ => Err(anyhow::anyhow!("invalid token")),
+            }
+        }
+    }
+
+    async fn call(path: &str, token: Option<&str>) -> Option<String> {
+        let service = ServiceAuthLayer::new(StaticVerifier)
+            .skip("/Validate.Validate/client_token")
+            .layer(service_fn(|request: Request<Body>| async move {
+                let user = request.extensions().get::<UserToken>().cloned();
+                let mut response = Response::new(tonic::body::empty_body());
+                response
+                    .extensions_mut()
+                    .insert(user.map(|user| user.user_name).unwrap_or_default());
+                Ok::<_, std::convert::Infallible>(response)
+            }));
+        let mut request = Request::post(path);
+        if let Some(token) = token {
+            request = request.header(SERVICE_TOKEN_KEY, token);
+        }
+        let response = service
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        match Status::from_header_map(response.headers()) {
+            Some(status) => Some(format!("{:?}", status.code())),
+            None => response.extensions().get::<String>().cloned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn service_token() {
+        let operate = "/Wallet.Wallet/operate";
+        assert_eq!(call(operate, Some("service")).await.unwrap(), "sale");
+        assert_eq!(
+            call(operate, Some("user")).await.unwrap(),
+            "Unauthenticated"
+        );
+        assert_eq!(
+            call(operate, Some("other")).await.unwrap(),
+            "Unauthenticated"
+        );
+        assert_eq!(call(operate, None).await.unwrap(), "Unauthenticated");
+        assert_eq!(
+            call("/Validate.Validate/client_token", None).await.unwrap(),
+            ""
+        );
+    }
+
+    #[tokio::test]
+    async fn remote_cache() {
+        let verifier = RemoteVerifier::new(Duration::from_millis(50));
+        let user = StaticVerifier.verify("service").await.unwrap();
+        verifier.store("service", &user);
+        assert_eq!(verifier.cached("service").unwrap().user_name, "sale");
+        // 没有过期的 token 也要在 ttl 之后重新向 login 校验
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(verifier.cached("service").is_none());
+
+        let expired = UserToken {
+            time_out: now() - 1,
+            ..user
+        };
+        verifier.store("expired", &expired);
+        assert!(verifier.cached("expired").is_none());
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Server, ServerTlsConfig,
+};
+
+use crate::config::Validate;
+
+lazy_static! {
+    // 没有配置时使用明文
+    static ref TLS_FILES: RwLock<Option<TlsFiles>> = RwLock::new(None);
+}
+
+// 都是 pem 文件，本地使用 deploy/certs/gen.sh 生成
+// 服务端校验客户端证书，客户端校验服务端证书，同一份证书两边都用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsFiles {
+    #[serde(default = "default_ca")]
+    pub ca: String,
+    pub cert: String,
+    // 默认是 cert 的 .pem 换成 .key
+    pub key: Option<String>,
+    // 服务端证书中的域名
+    #[serde(default = "default_domain")]
+    pub domain: String,
+}
+
+fn default_ca() -> String {
+    "deploy/certs/ca.pem".to_string()
+}
+
+fn default_domain() -> String {
+    "localhost".to_string()
+}
+
+fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| anyhow!("read {} err,{}", path, e))
+}
+
+impl TlsFiles {
+    pub fn key(&self) -> String {
+        self.key
+            .clone()
+            .unwrap_or(self.cert.replace(".pem", ".key"))
+    }
+
+    fn identity(&self) -> anyhow::Result<Identity> {
+        Ok(Identity::from_pem(read(&self.cert)?, read(&self.key())?))
+    }
+
+    pub fn server_config(&self) -> anyhow::Result<ServerTlsConfig> {
+        Ok(ServerTlsConfig::new()
+            .identity(self.identity()?)
+            .client_ca_root(Certificate::from_pem(read(&self.ca)?)))
+    }
+
+    pub fn client_config(&self) -> anyhow::Result<ClientTlsConfig> {
+        Ok(ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(read(&self.ca)?))
+            .identity(self.identity()?)
+            .domain_name(&self.domain))
+    }
+}
+
+impl Validate for TlsFiles {
+    fn validate(&self) -> anyhow::Result<()> {
+        for path in [&self.ca, &self.cert, &self.key()] {
+            if !Path::new(path).is_file() {
+                return Err(anyhow!("tls file {} not found", path));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 需要在创建 grpc 服务端和客户端之前调用
+pub fn init(files: Option<TlsFiles>) {
+    *TLS_FILES.write().unwrap() = files;
+}
+
+pub fn server() -> anyhow::Result<Server> {
+    let server = Server::builder();
+    Ok(match TLS_FILES.read().unwrap().as_ref() {
+        None => server,
+        Some(files) => server.tls_config(files.server_config()?)?,
+    })
+}
+
+// 使用 tls 时地址需要是 https://
+pub fn endpoint(url: String) -> anyhow::Result<Endpoint> {
+    let endpoint = Channel::from_shared(url)?;
+    Ok(match TLS_FILES.read().unwrap().as_ref() {
+        None => endpoint,
+        Some(files) => endpoint.tls_config(files.client_config()?)?,
+    })
+}
@@ -18,6 +18,9 @@ pub struct UserInfo {
     >,
     #[prost(int64, tag = "4")]
     pub time_out: i64,
+    /// 形如 wallet:debit
+    #[prost(string, repeated, tag = "5")]
+    pub scopes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -26,6 +29,8 @@ pub struct AuthRequest {
     pub user_id: u64,
     #[prost(string, tag = "2")]
     pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub method: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -40,6 +45,8 @@ pub struct ValidateRequest {
     pub token: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub method: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -56,6 +63,9 @@ pub struct AddUrlAuthRequest {
     pub url: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub auth: ::prost::alloc::string::String,
+    /// 为空时匹配所有方法
+    #[prost(string, tag = "3")]
+    pub method: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -75,6 +85,101 @@ pub struct GetAllAuthResponse {
     #[prost(string, repeated, tag = "1")]
     pub auth_infos: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClientTokenRequest {
+    #[prost(string, tag = "1")]
+    pub client_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub client_secret: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ClientTokenResponse {
+    #[prost(string, tag = "1")]
+    pub token: ::prost::alloc::string::String,
+    /// 有效期，秒
+    #[prost(int64, tag = "2")]
+    pub expires_in: i64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RevokedRequest {
+    #[prost(string, tag = "1")]
+    pub jti: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub sid: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub user_id: u64,
+    /// token 的 iat
+    #[prost(int64, tag = "4")]
+    pub issued_at: i64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RevokedResponse {
+    #[prost(bool, tag = "1")]
+    pub revoked: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RoleRequest {
+    #[prost(string, tag = "1")]
+    pub role_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UserRoleRequest {
+    #[prost(string, tag = "1")]
+    pub user_name: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub role_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UrlRoleRequest {
+    #[prost(string, tag = "1")]
+    pub method: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub role_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RoleAdminResponse {
+    #[prost(bool, tag = "1")]
+    pub ok: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRolesRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRolesResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub role_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UrlPermission {
+    #[prost(string, tag = "1")]
+    pub method: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub url: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RolePermissionsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub permissions: ::prost::alloc::vec::Vec<UrlPermission>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RoleMembersResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub user_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 /// Generated client implementations.
 pub mod validate_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -201,82 +306,790 @@ pub mod validate_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
-        pub async fn add_url_auth(
-            &mut self,
-            request: impl tonic::IntoRequest<super::AddUrlAuthRequest>,
-        ) -> Result<tonic::Response<super::AddUrlAuthResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/Validate.Validate/add_url_auth",
-            );
-            self.inner.unary(request.into_request(), path, codec).await
+        pub async fn add_url_auth(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AddUrlAuthRequest>,
+        ) -> Result<tonic::Response<super::AddUrlAuthResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.Validate/add_url_auth",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn get_all_auth(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetAllAuthRequest>,
+        ) -> Result<tonic::Response<super::GetAllAuthResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.Validate/get_all_auth",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// 服务之间调用使用的 token，client 在 login 的配置中
+        pub async fn client_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ClientTokenRequest>,
+        ) -> Result<tonic::Response<super::ClientTokenResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.Validate/client_token",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// 本地校验 token 的服务用来检查 logout、session 撤销和修改密码
+        pub async fn check_revoked(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RevokedRequest>,
+        ) -> Result<tonic::Response<super::RevokedResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.Validate/check_revoked",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod role_admin_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// 角色管理，只有 root 可以调用，token 放在 metadata 的 authorization 中
+    /// 和其他 rpc 一样还需要 x-service-token
+    #[derive(Debug, Clone)]
+    pub struct RoleAdminClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl RoleAdminClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> RoleAdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> RoleAdminClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            RoleAdminClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        pub async fn create_role(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/create_role",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn delete_role(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/delete_role",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn grant_user_role(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UserRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/grant_user_role",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn revoke_user_role(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UserRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/revoke_user_role",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn grant_url(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UrlRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/grant_url",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn revoke_url(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UrlRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/revoke_url",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_roles(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListRolesRequest>,
+        ) -> Result<tonic::Response<super::ListRolesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/list_roles",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_role_permissions(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RolePermissionsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/list_role_permissions",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_role_members(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleMembersResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/Validate.RoleAdmin/list_role_members",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod validate_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with ValidateServer.
+    #[async_trait]
+    pub trait Validate: Send + Sync + 'static {
+        async fn validate_token(
+            &self,
+            request: tonic::Request<super::TokenRequest>,
+        ) -> Result<tonic::Response<super::UserInfo>, tonic::Status>;
+        async fn validate_auth(
+            &self,
+            request: tonic::Request<super::AuthRequest>,
+        ) -> Result<tonic::Response<super::AuthResponse>, tonic::Status>;
+        async fn validate(
+            &self,
+            request: tonic::Request<super::ValidateRequest>,
+        ) -> Result<tonic::Response<super::ValidateResponse>, tonic::Status>;
+        async fn add_url_auth(
+            &self,
+            request: tonic::Request<super::AddUrlAuthRequest>,
+        ) -> Result<tonic::Response<super::AddUrlAuthResponse>, tonic::Status>;
+        async fn get_all_auth(
+            &self,
+            request: tonic::Request<super::GetAllAuthRequest>,
+        ) -> Result<tonic::Response<super::GetAllAuthResponse>, tonic::Status>;
+        /// 服务之间调用使用的 token，client 在 login 的配置中
+        async fn client_token(
+            &self,
+            request: tonic::Request<super::ClientTokenRequest>,
+        ) -> Result<tonic::Response<super::ClientTokenResponse>, tonic::Status>;
+        /// 本地校验 token 的服务用来检查 logout、session 撤销和修改密码
+        async fn check_revoked(
+            &self,
+            request: tonic::Request<super::RevokedRequest>,
+        ) -> Result<tonic::Response<super::RevokedResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct ValidateServer<T: Validate> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Validate> ValidateServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for ValidateServer<T>
+    where
+        T: Validate,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/Validate.Validate/validate_token" => {
+                    #[allow(non_camel_case_types)]
+                    struct validate_tokenSvc<T: Validate>(pub Arc<T>);
+                    impl<T: Validate> tonic::server::UnaryService<super::TokenRequest>
+                    for validate_tokenSvc<T> {
+                        type Response = super::UserInfo;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TokenRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).validate_token(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = validate_tokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/validate_auth" => {
+                    #[allow(non_camel_case_types)]
+                    struct validate_authSvc<T: Validate>(pub Arc<T>);
+                    impl<T: Validate> tonic::server::UnaryService<super::AuthRequest>
+                    for validate_authSvc<T> {
+                        type Response = super::AuthResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AuthRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).validate_auth(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = validate_authSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/validate" => {
+                    #[allow(non_camel_case_types)]
+                    struct validateSvc<T: Validate>(pub Arc<T>);
+                    impl<T: Validate> tonic::server::UnaryService<super::ValidateRequest>
+                    for validateSvc<T> {
+                        type Response = super::ValidateResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ValidateRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).validate(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = validateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/add_url_auth" => {
+                    #[allow(non_camel_case_types)]
+                    struct add_url_authSvc<T: Validate>(pub Arc<T>);
+                    impl<
+                        T: Validate,
+                    > tonic::server::UnaryService<super::AddUrlAuthRequest>
+                    for add_url_authSvc<T> {
+                        type Response = super::AddUrlAuthResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddUrlAuthRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).add_url_auth(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = add_url_authSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/get_all_auth" => {
+                    #[allow(non_camel_case_types)]
+                    struct get_all_authSvc<T: Validate>(pub Arc<T>);
+                    impl<
+                        T: Validate,
+                    > tonic::server::UnaryService<super::GetAllAuthRequest>
+                    for get_all_authSvc<T> {
+                        type Response = super::GetAllAuthResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetAllAuthRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_all_auth(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = get_all_authSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/client_token" => {
+                    #[allow(non_camel_case_types)]
+                    struct client_tokenSvc<T: Validate>(pub Arc<T>);
+                    impl<
+                        T: Validate,
+                    > tonic::server::UnaryService<super::ClientTokenRequest>
+                    for client_tokenSvc<T> {
+                        type Response = super::ClientTokenResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ClientTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).client_token(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = client_tokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.Validate/check_revoked" => {
+                    #[allow(non_camel_case_types)]
+                    struct check_revokedSvc<T: Validate>(pub Arc<T>);
+                    impl<T: Validate> tonic::server::UnaryService<super::RevokedRequest>
+                    for check_revokedSvc<T> {
+                        type Response = super::RevokedResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RevokedRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).check_revoked(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = check_revokedSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: Validate> Clone for ValidateServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
         }
-        pub async fn get_all_auth(
-            &mut self,
-            request: impl tonic::IntoRequest<super::GetAllAuthRequest>,
-        ) -> Result<tonic::Response<super::GetAllAuthResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/Validate.Validate/get_all_auth",
-            );
-            self.inner.unary(request.into_request(), path, codec).await
+    }
+    impl<T: Validate> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
         }
     }
+    impl<T: Validate> tonic::server::NamedService for ValidateServer<T> {
+        const NAME: &'static str = "Validate.Validate";
+    }
 }
 /// Generated server implementations.
-pub mod validate_server {
+pub mod role_admin_server {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
     use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with ValidateServer.
+    /// Generated trait containing gRPC methods that should be implemented for use with RoleAdminServer.
     #[async_trait]
-    pub trait Validate: Send + Sync + 'static {
-        async fn validate_token(
+    pub trait RoleAdmin: Send + Sync + 'static {
+        async fn create_role(
             &self,
-            request: tonic::Request<super::TokenRequest>,
-        ) -> Result<tonic::Response<super::UserInfo>, tonic::Status>;
-        async fn validate_auth(
+            request: tonic::Request<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn delete_role(
             &self,
-            request: tonic::Request<super::AuthRequest>,
-        ) -> Result<tonic::Response<super::AuthResponse>, tonic::Status>;
-        async fn validate(
+            request: tonic::Request<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn grant_user_role(
             &self,
-            request: tonic::Request<super::ValidateRequest>,
-        ) -> Result<tonic::Response<super::ValidateResponse>, tonic::Status>;
-        async fn add_url_auth(
+            request: tonic::Request<super::UserRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn revoke_user_role(
             &self,
-            request: tonic::Request<super::AddUrlAuthRequest>,
-        ) -> Result<tonic::Response<super::AddUrlAuthResponse>, tonic::Status>;
-        async fn get_all_auth(
+            request: tonic::Request<super::UserRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn grant_url(
             &self,
-            request: tonic::Request<super::GetAllAuthRequest>,
-        ) -> Result<tonic::Response<super::GetAllAuthResponse>, tonic::Status>;
+            request: tonic::Request<super::UrlRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn revoke_url(
+            &self,
+            request: tonic::Request<super::UrlRoleRequest>,
+        ) -> Result<tonic::Response<super::RoleAdminResponse>, tonic::Status>;
+        async fn list_roles(
+            &self,
+            request: tonic::Request<super::ListRolesRequest>,
+        ) -> Result<tonic::Response<super::ListRolesResponse>, tonic::Status>;
+        async fn list_role_permissions(
+            &self,
+            request: tonic::Request<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RolePermissionsResponse>, tonic::Status>;
+        async fn list_role_members(
+            &self,
+            request: tonic::Request<super::RoleRequest>,
+        ) -> Result<tonic::Response<super::RoleMembersResponse>, tonic::Status>;
     }
+    /// 角色管理，只有 root 可以调用，token 放在 metadata 的 authorization 中
+    /// 和其他 rpc 一样还需要 x-service-token
     #[derive(Debug)]
-    pub struct ValidateServer<T: Validate> {
+    pub struct RoleAdminServer<T: RoleAdmin> {
         inner: _Inner<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
     }
     struct _Inner<T>(Arc<T>);
-    impl<T: Validate> ValidateServer<T> {
+    impl<T: RoleAdmin> RoleAdminServer<T> {
         pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
@@ -310,9 +1123,9 @@ pub mod validate_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for ValidateServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for RoleAdminServer<T>
     where
-        T: Validate,
+        T: RoleAdmin,
         B: Body + Send + 'static,
         B::Error: Into<StdError> + Send + 'static,
     {
@@ -328,23 +1141,97 @@ pub mod validate_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
-                "/Validate.Validate/validate_token" => {
+                "/Validate.RoleAdmin/create_role" => {
                     #[allow(non_camel_case_types)]
-                    struct validate_tokenSvc<T: Validate>(pub Arc<T>);
-                    impl<T: Validate> tonic::server::UnaryService<super::TokenRequest>
-                    for validate_tokenSvc<T> {
-                        type Response = super::UserInfo;
+                    struct create_roleSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::RoleRequest>
+                    for create_roleSvc<T> {
+                        type Response = super::RoleAdminResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::TokenRequest>,
+                            request: tonic::Request<super::RoleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).create_role(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = create_roleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.RoleAdmin/delete_role" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_roleSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::RoleRequest>
+                    for delete_roleSvc<T> {
+                        type Response = super::RoleAdminResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RoleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).delete_role(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_roleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.RoleAdmin/grant_user_role" => {
+                    #[allow(non_camel_case_types)]
+                    struct grant_user_roleSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<
+                        T: RoleAdmin,
+                    > tonic::server::UnaryService<super::UserRoleRequest>
+                    for grant_user_roleSvc<T> {
+                        type Response = super::RoleAdminResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UserRoleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).validate_token(request).await
+                                (*inner).grant_user_role(request).await
                             };
                             Box::pin(fut)
                         }
@@ -354,7 +1241,7 @@ pub mod validate_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = validate_tokenSvc(inner);
+                        let method = grant_user_roleSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -366,23 +1253,25 @@ pub mod validate_server {
                     };
                     Box::pin(fut)
                 }
-                "/Validate.Validate/validate_auth" => {
+                "/Validate.RoleAdmin/revoke_user_role" => {
                     #[allow(non_camel_case_types)]
-                    struct validate_authSvc<T: Validate>(pub Arc<T>);
-                    impl<T: Validate> tonic::server::UnaryService<super::AuthRequest>
-                    for validate_authSvc<T> {
-                        type Response = super::AuthResponse;
+                    struct revoke_user_roleSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<
+                        T: RoleAdmin,
+                    > tonic::server::UnaryService<super::UserRoleRequest>
+                    for revoke_user_roleSvc<T> {
+                        type Response = super::RoleAdminResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AuthRequest>,
+                            request: tonic::Request<super::UserRoleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).validate_auth(request).await
+                                (*inner).revoke_user_role(request).await
                             };
                             Box::pin(fut)
                         }
@@ -392,7 +1281,7 @@ pub mod validate_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = validate_authSvc(inner);
+                        let method = revoke_user_roleSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -404,22 +1293,22 @@ pub mod validate_server {
                     };
                     Box::pin(fut)
                 }
-                "/Validate.Validate/validate" => {
+                "/Validate.RoleAdmin/grant_url" => {
                     #[allow(non_camel_case_types)]
-                    struct validateSvc<T: Validate>(pub Arc<T>);
-                    impl<T: Validate> tonic::server::UnaryService<super::ValidateRequest>
-                    for validateSvc<T> {
-                        type Response = super::ValidateResponse;
+                    struct grant_urlSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::UrlRoleRequest>
+                    for grant_urlSvc<T> {
+                        type Response = super::RoleAdminResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ValidateRequest>,
+                            request: tonic::Request<super::UrlRoleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).validate(request).await };
+                            let fut = async move { (*inner).grant_url(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -428,7 +1317,7 @@ pub mod validate_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = validateSvc(inner);
+                        let method = grant_urlSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -440,25 +1329,97 @@ pub mod validate_server {
                     };
                     Box::pin(fut)
                 }
-                "/Validate.Validate/add_url_auth" => {
+                "/Validate.RoleAdmin/revoke_url" => {
                     #[allow(non_camel_case_types)]
-                    struct add_url_authSvc<T: Validate>(pub Arc<T>);
+                    struct revoke_urlSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::UrlRoleRequest>
+                    for revoke_urlSvc<T> {
+                        type Response = super::RoleAdminResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UrlRoleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).revoke_url(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = revoke_urlSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.RoleAdmin/list_roles" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_rolesSvc<T: RoleAdmin>(pub Arc<T>);
                     impl<
-                        T: Validate,
-                    > tonic::server::UnaryService<super::AddUrlAuthRequest>
-                    for add_url_authSvc<T> {
-                        type Response = super::AddUrlAuthResponse;
+                        T: RoleAdmin,
+                    > tonic::server::UnaryService<super::ListRolesRequest>
+                    for list_rolesSvc<T> {
+                        type Response = super::ListRolesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AddUrlAuthRequest>,
+                            request: tonic::Request<super::ListRolesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_roles(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_rolesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/Validate.RoleAdmin/list_role_permissions" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_role_permissionsSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::RoleRequest>
+                    for list_role_permissionsSvc<T> {
+                        type Response = super::RolePermissionsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RoleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).add_url_auth(request).await
+                                (*inner).list_role_permissions(request).await
                             };
                             Box::pin(fut)
                         }
@@ -468,7 +1429,7 @@ pub mod validate_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = add_url_authSvc(inner);
+                        let method = list_role_permissionsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -480,25 +1441,23 @@ pub mod validate_server {
                     };
                     Box::pin(fut)
                 }
-                "/Validate.Validate/get_all_auth" => {
+                "/Validate.RoleAdmin/list_role_members" => {
                     #[allow(non_camel_case_types)]
-                    struct get_all_authSvc<T: Validate>(pub Arc<T>);
-                    impl<
-                        T: Validate,
-                    > tonic::server::UnaryService<super::GetAllAuthRequest>
-                    for get_all_authSvc<T> {
-                        type Response = super::GetAllAuthResponse;
+                    struct list_role_membersSvc<T: RoleAdmin>(pub Arc<T>);
+                    impl<T: RoleAdmin> tonic::server::UnaryService<super::RoleRequest>
+                    for list_role_membersSvc<T> {
+                        type Response = super::RoleMembersResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetAllAuthRequest>,
+                            request: tonic::Request<super::RoleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).get_all_auth(request).await
+                                (*inner).list_role_members(request).await
                             };
                             Box::pin(fut)
                         }
@@ -508,7 +1467,7 @@ pub mod validate_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = get_all_authSvc(inner);
+                        let method = list_role_membersSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -535,7 +1494,7 @@ pub mod validate_server {
             }
         }
     }
-    impl<T: Validate> Clone for ValidateServer<T> {
+    impl<T: RoleAdmin> Clone for RoleAdminServer<T> {
         fn clone(&self) -> Self {
             let inner = self.inner.clone();
             Self {
@@ -545,7 +1504,7 @@ pub mod validate_server {
             }
         }
     }
-    impl<T: Validate> Clone for _Inner<T> {
+    impl<T: RoleAdmin> Clone for _Inner<T> {
         fn clone(&self) -> Self {
             Self(self.0.clone())
         }
@@ -555,7 +1514,7 @@ pub mod validate_server {
             write!(f, "{:?}", self.0)
         }
     }
-    impl<T: Validate> tonic::server::NamedService for ValidateServer<T> {
-        const NAME: &'static str = "Validate.Validate";
+    impl<T: RoleAdmin> tonic::server::NamedService for RoleAdminServer<T> {
+        const NAME: &'static str = "Validate.RoleAdmin";
     }
 }
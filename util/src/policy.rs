@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use crate::axum::auth::{Home, UserToken};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Read,
+    Update,
+    Delete,
+    Pay,
+    Cancel,
+    Sign,
+    Consult,
+    Discard,
+    Operate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Record,
+    Comment,
+    Home,
+    Balance,
+}
+
+// 谁可以执行操作，角色包括通过继承得到的
+#[derive(Debug, Clone, Copy)]
+pub enum Rule {
+    Owner,
+    Roles(&'static [&'static str]),
+    OwnerOr(&'static [&'static str]),
+}
+
+// root 继承了 worker
+const STAFF: &[&str] = &["worker"];
+const ROOT: &[&str] = &["root"];
+
+// 没有列出的操作一律拒绝
+pub const RULES: &[(Kind, Action, Rule)] = &[
+    (Kind::Record, Action::Read, Rule::OwnerOr(STAFF)),
+    (Kind::Record, Action::Update, Rule::Owner),
+    (Kind::Record, Action::Pay, Rule::Owner),
+    (Kind::Record, Action::Cancel, Rule::Owner),
+    (Kind::Record, Action::Sign, Rule::OwnerOr(STAFF)),
+    (Kind::Record, Action::Consult, Rule::Owner),
+    // 售后中的订单由 root 作废
+    (Kind::Record, Action::Discard, Rule::Roles(ROOT)),
+    (Kind::Comment, Action::Update, Rule::Owner),
+    (Kind::Comment, Action::Delete, Rule::OwnerOr(ROOT)),
+    (Kind::Home, Action::Read, Rule::OwnerOr(ROOT)),
+    (Kind::Home, Action::Update, Rule::Owner),
+    (Kind::Home, Action::Delete, Rule::Owner),
+    (Kind::Balance, Action::Read, Rule::OwnerOr(ROOT)),
+    (Kind::Balance, Action::Update, Rule::Owner),
+    (Kind::Balance, Action::Operate, Rule::Roles(ROOT)),
+];
+
+pub trait Resource {
+    const KIND: Kind;
+    fn owner_id(&self) -> u64;
+}
+
+impl Resource for Home {
+    const KIND: Kind = Kind::Home;
+    fn owner_id(&self) -> u64 {
+        self.user_id
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Subject {
+    pub user_id: u64,
+    pub roles: HashSet<String>,
+}
+
+impl Subject {
+    pub fn new(user_id: u64, roles: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            user_id,
+            roles: roles.into_iter().collect(),
+        }
+    }
+
+    pub async fn from_token(user: &UserToken) -> Self {
+        Self::new(user.user_id, user.get_auths().await.unwrap_or_default())
+    }
+
+    fn has_any(&self, roles: &[&str]) -> bool {
+        roles.iter().any(|role| self.roles.contains(*role))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forbidden {
+    pub user_id: u64,
+    pub action: Action,
+    pub kind: Kind,
+}
+
+impl Display for Forbidden {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "forbidden: user {} can't {:?} {:?}",
+            self.user_id, self.action, self.kind
+        )
+    }
+}
+
+impl std::error::Error for Forbidden {}
+
+pub trait Policy {
+    fn can<R: Resource>(&self, user: &Subject, action: Action, resource: &R) -> bool;
+
+    fn check<R: Resource>(
+        &self,
+        user: &Subject,
+        action: Action,
+        resource: &R,
+    ) -> Result<(), Forbidden> {
+        if self.can(user, action, resource) {
+            return Ok(());
+        }
+        Err(Forbidden {
+            user_id: user.user_id,
+            action,
+            kind: R::KIND,
+        })
+    }
+}
+
+pub struct RulePolicy {
+    rules: &'static [(Kind, Action, Rule)],
+}
+
+impl RulePolicy {
+    pub const fn new(rules: &'static [(Kind, Action, Rule)]) -> Self {
+        Self { rules }
+    }
+}
+
+impl Policy for RulePolicy {
+    fn can<R: Resource>(&self, user: &Subject, action: Action, resource: &R) -> bool {
+        let rule = self
+            .rules
+            .iter()
+            .find(|(kind, act, _)| *kind == R::KIND && *act == action);
+        let is_owner = resource.owner_id() == user.user_id;
+        match rule {
+            None => false,
+            Some((_, _, Rule::Owner)) => is_owner,
+            Some((_, _, Rule::Roles(roles))) => user.has_any(roles),
+            Some((_, _, Rule::OwnerOr(roles))) => is_owner || user.has_any(roles),
+        }
+    }
+}
+
+pub static POLICY: RulePolicy = RulePolicy::new(RULES);
+
+pub fn can<R: Resource>(user: &Subject, action: Action, resource: &R) -> bool {
+    POLICY.can(user, action, resource)
+}
+
+pub fn authorize<R: Resource>(
+    user: &Subject,
+    action: Action,
+    resource: &R,
+) -> Result<(), Forbidden> {
+    POLICY.check(user, action, resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Record(u64);
+
+    impl Resource for Record {
+        const KIND: Kind = Kind::Record;
+        fn owner_id(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let owner = Subject::new(1, []);
+        let worker = Subject::new(2, ["worker".to_string(), "normal".to_string()]);
+        let record = Record(1);
+
+        assert!(can(&owner, Action::Pay, &record));
+        assert!(!can(&worker, Action::Pay, &record));
+        assert!(can(&worker, Action::Sign, &record));
+        assert!(!can(&worker, Action::Discard, &record));
+        assert!(!can(&owner, Action::Discard, &record));
+        assert!(can(
+            &Subject::new(3, ["root".to_string()]),
+            Action::Discard,
+            &record
+        ));
+        // 没有规则的操作拒绝
+        assert!(!can(&owner, Action::Operate, &record));
+
+        let home = Home {
+            user_id: 1,
+            ..Default::default()
+        };
+        assert!(!can(&worker, Action::Read, &home));
+        assert!(can(
+            &Subject::new(3, ["root".to_string()]),
+            Action::Read,
+            &home
+        ));
+    }
+
+    #[test]
+    fn forbidden() {
+        let err = authorize(&Subject::new(2, []), Action::Update, &Record(1)).unwrap_err();
+        assert_eq!(
+            err,
+            Forbidden {
+                user_id: 2,
+                action: Action::Update,
+                kind: Kind::Record,
+            }
+        );
+        assert_eq!(err.to_string(), "forbidden: user 2 can't Update Record");
+    }
+}
@@ -7,7 +7,9 @@ macro_rules! get {
                 rb: &mut dyn rbatis::executor::Executor,
                 $column_value: $column_type,
             ) -> Option<Self> {
-                match <$rb_type>::select_by_column(rb, &stringify!($column_name), $column_value).await {
+                let query = <$rb_type>::select_by_column(rb, &stringify!($column_name), $column_value);
+                let op = concat!(stringify!($rb_type), ".get_by_", stringify!($column_name));
+                match $crate::rbatis::init::timed(op, query).await {
                     Ok(mut maybe) => maybe.pop(),
                     _ => None,
                 }
@@ -20,7 +22,10 @@ macro_rules! get {
             pub async fn [<select_by_$column_name>](
                 $column_value: $column_type,
             ) -> Option<Self> {
-                match <$rb_type>::select_by_column(&mut $rb.clone(), &stringify!($column_name), $column_value).await {
+                let mut rb = $rb.clone();
+                let query = <$rb_type>::select_by_column(&mut rb, &stringify!($column_name), $column_value);
+                let op = concat!(stringify!($rb_type), ".select_by_", stringify!($column_name));
+                match $crate::rbatis::init::timed(op, query).await {
                     Ok(mut maybe) => maybe.pop(),
                     _ => None,
                 }
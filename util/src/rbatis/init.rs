@@ -1,8 +1,28 @@
+use std::future::Future;
+use std::time::Instant;
+
 use async_trait::async_trait;
 use log::error;
 use rbatis::executor::RBatisTxExecutorGuard;
 use rbatis::Rbatis;
 
+use crate::metrics::{Gauge, Histogram, LATENCY_BUCKETS};
+
+static DB_QUERY_DURATION: Histogram = Histogram::new(
+    "db_query_duration_seconds",
+    "database query latency by op and result",
+    LATENCY_BUCKETS,
+);
+static DB_ACQUIRE_DURATION: Histogram = Histogram::new(
+    "db_acquire_duration_seconds",
+    "time to get a pooled connection and begin a transaction",
+    LATENCY_BUCKETS,
+);
+static DB_TX_IN_FLIGHT: Gauge = Gauge::new(
+    "db_transactions_in_flight",
+    "transactions holding a pooled connection",
+);
+
 #[macro_export]
 macro_rules! insert {
     ($($table:ty),*) => {
@@ -57,17 +77,41 @@ macro_rules! init {
                 <$init>::init().await;
             )*
         }
+        // 用于 /readyz，检查连接池能否拿到可用的连接
+        pub async fn ping() -> anyhow::Result<()> {
+            DB.exec("select 1", vec![]).await?;
+            Ok(())
+        }
     };
 }
 
+// 等待连接的时间和进行中的事务数可以看出连接池是否够用
 pub async fn get_tx_set_defer(db: Rbatis) -> anyhow::Result<RBatisTxExecutorGuard> {
-    let tx_no_defer = db.acquire_begin().await?;
+    let start = Instant::now();
+    let tx_no_defer = db.acquire_begin().await;
+    let result = if tx_no_defer.is_ok() { "ok" } else { "err" };
+    DB_ACQUIRE_DURATION.observe(&[("result", result)], start.elapsed().as_secs_f64());
+    let tx_no_defer = tx_no_defer?;
+    DB_TX_IN_FLIGHT.add(&[], 1.0);
     let tx = tx_no_defer.defer_async(|mut tx| async move {
         if !tx.done {
             if let Err(e) = tx.rollback().await {
                 error!("run defer rollback err {}", e)
             };
         }
+        DB_TX_IN_FLIGHT.add(&[], -1.0);
     });
     Ok(tx)
 }
+
+// 用于统计单条查询的耗时，op 一般是 表名.方法名
+pub async fn timed<T, E>(op: &str, query: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+    let start = Instant::now();
+    let result = query.await;
+    let labels = [
+        ("op", op),
+        ("result", if result.is_ok() { "ok" } else { "err" }),
+    ];
+    DB_QUERY_DURATION.observe(&labels, start.elapsed().as_secs_f64());
+    result
+}
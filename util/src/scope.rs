@@ -0,0 +1,130 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// 形如 wallet:debit，action 为 * 时包括该资源的所有操作
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Scope {
+    pub resource: String,
+    pub action: String,
+}
+
+impl Scope {
+    pub fn new(resource: &str, action: &str) -> Self {
+        Self {
+            resource: resource.to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    pub fn allows(&self, need: &Scope) -> bool {
+        self.resource == need.resource && (self.action == "*" || self.action == need.action)
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        };
+        match s.split_once(':') {
+            Some((resource, action)) if valid(resource) && (action == "*" || valid(action)) => {
+                Ok(Self::new(resource, action))
+            }
+            _ => Err(anyhow!("invalid scope {}", s)),
+        }
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.resource, self.action)
+    }
+}
+
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+pub fn allows(scopes: &[Scope], need: &str) -> bool {
+    match need.parse::<Scope>() {
+        Ok(need) => scopes.iter().any(|scope| scope.allows(&need)),
+        Err(_) => false,
+    }
+}
+
+// 用于 RequireScope 的类型参数
+pub trait ScopeName {
+    const SCOPE: &'static str;
+}
+
+macro_rules! scope_names {
+    ($($name:ident => $scope:expr),+ $(,)?) => {
+        $(
+            pub struct $name;
+            impl ScopeName for $name {
+                const SCOPE: &'static str = $scope;
+            }
+        )+
+    };
+}
+
+scope_names!(
+    WalletRead => "wallet:read",
+    WalletCredit => "wallet:credit",
+    WalletDebit => "wallet:debit",
+    WalletAdmin => "wallet:admin",
+    HomeRead => "home:read",
+    RecordSend => "record:send",
+    AuthRegister => "auth:register",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let scope: Scope = "wallet:debit".parse().unwrap();
+        assert_eq!(scope, Scope::new("wallet", "debit"));
+        assert_eq!(scope.to_string(), "wallet:debit");
+        assert!("wallet".parse::<Scope>().is_err());
+        assert!("wallet:".parse::<Scope>().is_err());
+        assert!("*:debit".parse::<Scope>().is_err());
+        assert!("Wallet:debit".parse::<Scope>().is_err());
+
+        let scopes: Vec<Scope> = serde_json::from_str(r#"["wallet:*","record:send"]"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&scopes).unwrap(),
+            r#"["wallet:*","record:send"]"#
+        );
+        assert!(serde_json::from_str::<Vec<Scope>>(r#"["wallet"]"#).is_err());
+    }
+
+    #[test]
+    fn allow() {
+        let scopes = vec![Scope::new("wallet", "*"), "record:send".parse().unwrap()];
+        assert!(allows(&scopes, WalletDebit::SCOPE));
+        assert!(allows(&scopes, RecordSend::SCOPE));
+        assert!(!allows(&scopes, "record:sign"));
+        assert!(!allows(&scopes, HomeRead::SCOPE));
+        assert!(!allows(&scopes, "invalid"));
+    }
+}
@@ -0,0 +1,517 @@
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::sync::RwLock;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use axum::extract::MatchedPath;
+use futures::future::BoxFuture;
+use hyper::header::HeaderValue;
+use hyper::{HeaderMap, Request, Response};
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tonic::metadata::MetadataMap;
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+use crate::config::{check_url, Validate};
+
+// W3C trace context，格式为 00-<trace_id>-<span_id>-<flags>
+pub const TRACEPARENT: &str = "traceparent";
+// 没有带上时使用 trace_id，响应中原样返回
+pub const REQUEST_ID: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+lazy_static! {
+    static ref EXPORTER: RwLock<Option<mpsc::Sender<SpanData>>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_id: Option<String>,
+    pub sampled: bool,
+    pub request_id: String,
+}
+
+fn random_id(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    loop {
+        let id: String = (0..bytes)
+            .map(|_| format!("{:02x}", rng.gen::<u8>()))
+            .collect();
+        // 全 0 是无效的 id
+        if id.bytes().any(|b| b != b'0') {
+            return id;
+        }
+    }
+}
+
+fn is_hex_id(id: &str, len: usize) -> bool {
+    id.len() == len
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        && id.bytes().any(|b| b != b'0')
+}
+
+impl TraceContext {
+    pub fn root(request_id: Option<String>) -> Self {
+        let trace_id = random_id(16);
+        Self {
+            request_id: request_id.unwrap_or(trace_id.clone()),
+            trace_id,
+            span_id: random_id(8),
+            parent_id: None,
+            sampled: true,
+        }
+    }
+
+    // 返回 (trace_id, parent_id, sampled)，不合法时返回 None
+    pub fn parse(traceparent: &str) -> Option<(String, String, bool)> {
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+        match parts[..] {
+            [version, trace_id, parent_id, flags, ..]
+                if version.len() == 2
+                    && version != "ff"
+                    && (version != "00" || parts.len() == 4)
+                    && is_hex_id(trace_id, 32)
+                    && is_hex_id(parent_id, 16)
+                    && flags.len() == 2 =>
+            {
+                let flags = u8::from_str_radix(flags, 16).ok()?;
+                Some((trace_id.to_string(), parent_id.to_string(), flags & 1 == 1))
+            }
+            _ => None,
+        }
+    }
+
+    // 服务端收到请求时，以调用方的 span 为 parent 开始新的 span
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let request_id = headers
+            .get(REQUEST_ID)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+            .map(String::from);
+        let remote = headers
+            .get(TRACEPARENT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::parse);
+        match remote {
+            Some((trace_id, parent_id, sampled)) => Self {
+                request_id: request_id.unwrap_or(trace_id.clone()),
+                trace_id,
+                span_id: random_id(8),
+                parent_id: Some(parent_id),
+                sampled,
+            },
+            None => Self::root(request_id),
+        }
+    }
+
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_id(8),
+            parent_id: Some(self.span_id.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub fn traceparent(&self) -> String {
+        let flags = if self.sampled { "01" } else { "00" };
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, flags)
+    }
+}
+
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|ctx| ctx.clone()).ok()
+}
+
+pub async fn scope<F: Future>(ctx: TraceContext, f: F) -> F::Output {
+    CURRENT.scope(ctx, f).await
+}
+
+// 调用其他服务时带上当前的 trace，没有时不带
+pub fn inject(metadata: &mut MetadataMap) {
+    if let Some(ctx) = current() {
+        if let Ok(value) = ctx.traceparent().parse() {
+            metadata.insert(TRACEPARENT, value);
+        }
+        if let Ok(value) = ctx.request_id.parse() {
+            metadata.insert(REQUEST_ID, value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpanKind {
+    Server = 2,
+    Client = 3,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpanData {
+    pub ctx: TraceContext,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub error: bool,
+    pub attributes: Vec<(&'static str, String)>,
+}
+
+impl SpanData {
+    pub fn new(ctx: &TraceContext, name: String, kind: SpanKind, start: SystemTime) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            name,
+            kind,
+            start,
+            end: SystemTime::now(),
+            error: false,
+            attributes: vec![],
+        }
+    }
+
+    fn to_otlp(&self) -> Value {
+        let nanos = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+                .to_string()
+        };
+        let attributes: Vec<Value> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({"key": key, "value": {"stringValue": value}}))
+            .collect();
+        json!({
+            "traceId": self.ctx.trace_id,
+            "spanId": self.ctx.span_id,
+            "parentSpanId": self.ctx.parent_id.clone().unwrap_or_default(),
+            "name": self.name,
+            "kind": self.kind as i32,
+            "startTimeUnixNano": nanos(self.start),
+            "endTimeUnixNano": nanos(self.end),
+            "attributes": attributes,
+            // 1 是 OK，2 是 ERROR
+            "status": {"code": if self.error { 2 } else { 1 }},
+        })
+    }
+}
+
+// 没有配置 exporter 或者没有采样时丢弃，队列满时也丢弃，不阻塞请求
+pub fn export(span: SpanData) {
+    if !span.ctx.sampled {
+        return;
+    }
+    if let Some(sender) = EXPORTER.read().unwrap().as_ref() {
+        let _ = sender.try_send(span);
+    }
+}
+
+// otlp_endpoint 和 file 都不配置时不导出，只在日志中带上 trace_id
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TraceConfig {
+    // OTLP/HTTP 的 json 格式，例如 http://localhost:4318/v1/traces
+    pub otlp_endpoint: Option<String>,
+    // 每行一个 OTLP json，和 collector 的 file exporter 格式一致
+    pub file: Option<String>,
+    pub batch_size: usize,
+    pub flush_ms: u64,
+    pub queue_size: usize,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            file: None,
+            batch_size: 512,
+            flush_ms: 1000,
+            queue_size: 4096,
+        }
+    }
+}
+
+impl Validate for TraceConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(endpoint) = &self.otlp_endpoint {
+            check_url("trace.otlp_endpoint", endpoint, &["http"])?;
+        }
+        if self.batch_size == 0 || self.queue_size == 0 {
+            return Err(anyhow!("trace.batch_size and trace.queue_size should > 0"));
+        }
+        Ok(())
+    }
+}
+
+// 需要在 tokio runtime 中调用
+pub fn init(service: &str, config: &TraceConfig) {
+    if config.otlp_endpoint.is_none() && config.file.is_none() {
+        return;
+    }
+    let (sender, receiver) = mpsc::channel(config.queue_size);
+    *EXPORTER.write().unwrap() = Some(sender);
+    tokio::spawn(run_exporter(service.to_string(), config.clone(), receiver));
+}
+
+fn otlp_request(service: &str, spans: &[SpanData]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [{"key": "service.name", "value": {"stringValue": service}}]
+            },
+            "scopeSpans": [{
+                "scope": {"name": "supermarket"},
+                "spans": spans.iter().map(SpanData::to_otlp).collect::<Vec<_>>(),
+            }]
+        }]
+    })
+}
+
+async fn run_exporter(
+    service: String,
+    config: TraceConfig,
+    mut receiver: mpsc::Receiver<SpanData>,
+) {
+    let client = hyper::Client::new();
+    let mut batch = Vec::with_capacity(config.batch_size);
+    let mut ticker = tokio::time::interval(Duration::from_millis(config.flush_ms));
+    loop {
+        let closed = tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    if batch.len() < config.batch_size {
+                        continue;
+                    }
+                    false
+                }
+                None => true,
+            },
+            _ = ticker.tick() => false,
+        };
+        if !batch.is_empty() {
+            let body = otlp_request(&service, &batch).to_string();
+            batch.clear();
+            if let Err(e) = flush(&client, &config, body).await {
+                tracing::warn!("export spans err,{:#}", e);
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+async fn flush(
+    client: &hyper::Client<hyper::client::HttpConnector>,
+    config: &TraceConfig,
+    body: String,
+) -> anyhow::Result<()> {
+    if let Some(path) = &config.file {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", body)?;
+    }
+    if let Some(endpoint) = &config.otlp_endpoint {
+        let request = Request::post(endpoint)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))?;
+        let status = client.request(request).await?.status();
+        if !status.is_success() {
+            return Err(anyhow!("collector returned {}", status));
+        }
+    }
+    Ok(())
+}
+
+// http 和 grpc 的服务端共用，从 header 中取出 trace，处理请求时放在 CURRENT 中
+// 响应中带上 x-request-id 和当前的 traceparent
+#[derive(Clone, Default)]
+pub struct TraceContextLayer;
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContextService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct TraceContextService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TraceContextService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let ctx = TraceContext::from_headers(request.headers());
+        // http 使用路由模板，grpc 的 path 就是 /package.Service/method
+        let path = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or(request.uri().path().to_string());
+        let name = format!("{} {}", request.method(), path);
+        let span = tracing::info_span!(
+            "request",
+            trace_id = %ctx.trace_id,
+            request_id = %ctx.request_id,
+        );
+        let start = SystemTime::now();
+        let future = CURRENT.sync_scope(ctx.clone(), || self.inner.call(request));
+        Box::pin(async move {
+            let mut result = CURRENT.scope(ctx.clone(), future).instrument(span).await;
+            let mut data = SpanData::new(&ctx, name, SpanKind::Server, start);
+            match &mut result {
+                Ok(response) => {
+                    let status = response.status();
+                    let grpc_status = response
+                        .headers()
+                        .get("grpc-status")
+                        .map(|status| status.as_bytes() != b"0");
+                    data.error = status.is_server_error() || grpc_status.unwrap_or(false);
+                    data.attributes
+                        .push(("http.status_code", status.as_u16().to_string()));
+                    let headers = response.headers_mut();
+                    if let Ok(value) = HeaderValue::from_str(&ctx.request_id) {
+                        headers.insert(REQUEST_ID, value);
+                    }
+                    if let Ok(value) = HeaderValue::from_str(&ctx.traceparent()) {
+                        headers.insert(TRACEPARENT, value);
+                    }
+                }
+                Err(_) => data.error = true,
+            }
+            export(data);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Body;
+    use tower::{service_fn, ServiceExt};
+
+    #[test]
+    fn traceparent() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let (trace_id, parent_id, sampled) = TraceContext::parse(header).unwrap();
+        assert_eq!(trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent_id, "00f067aa0ba902b7");
+        assert!(sampled);
+
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert!(TraceContext::parse(invalid).is_none(), "{}", invalid);
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT, header.parse().unwrap());
+        let ctx = TraceContext::from_headers(&headers);
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(ctx.request_id, ctx.trace_id);
+        let child = ctx.child();
+        assert_eq!(child.parent_id, Some(ctx.span_id.clone()));
+        assert_eq!(
+            TraceContext::parse(&child.traceparent()),
+            Some((ctx.trace_id.clone(), child.span_id.clone(), true))
+        );
+    }
+
+    #[tokio::test]
+    async fn propagate() {
+        // 处理请求时可以拿到 trace，调用下游时 inject 到 metadata 中
+        let service = TraceContextLayer.layer(service_fn(|_: Request<Body>| async {
+            let mut metadata = MetadataMap::new();
+            inject(&mut metadata);
+            let traceparent = metadata.get(TRACEPARENT).unwrap().to_str().unwrap();
+            Ok::<_, anyhow::Error>(Response::new(Body::from(traceparent.to_string())))
+        }));
+        let request = Request::get("/pay")
+            .header(
+                TRACEPARENT,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .header(REQUEST_ID, "req-1")
+            .body(Body::empty())
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID], "req-1");
+        let traceparent = response.headers()[TRACEPARENT]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, traceparent);
+        assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(current().is_none());
+    }
+
+    #[tokio::test]
+    async fn export_file() {
+        let path = std::env::temp_dir().join("supermarket-trace-test.json");
+        let _ = std::fs::remove_file(&path);
+        let config = TraceConfig {
+            file: Some(path.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let ctx = TraceContext::root(None).child();
+        let mut span = SpanData::new(
+            &ctx,
+            "validate/validate".to_string(),
+            SpanKind::Client,
+            SystemTime::now(),
+        );
+        span.error = true;
+        let body = otlp_request("sale", &[span]).to_string();
+        flush(&hyper::Client::new(), &config, body).await.unwrap();
+
+        let line = std::fs::read_to_string(&path).unwrap();
+        let request: Value = serde_json::from_str(line.trim()).unwrap();
+        let resource = &request["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "sale"
+        );
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], ctx.trace_id);
+        assert_eq!(span["parentSpanId"], ctx.parent_id.unwrap());
+        assert_eq!(span["kind"], 3);
+        assert_eq!(span["status"]["code"], 2);
+    }
+}